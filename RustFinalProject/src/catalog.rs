@@ -0,0 +1,195 @@
+use crate::query::date_key;
+use crate::{MyError, NewItem, Placement, Position, Quality};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Display, Formatter};
+
+// What a product is, independent of how much of it is in stock
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Product {
+    pub sku: String,
+    pub name: String,
+    pub default_quality: Quality, // a Fragile product's date is replaced per lot
+}
+
+// Bookkeeping for a stored item that was received as a lot of a product
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(crate) struct LotInfo {
+    sku: String,
+    lot_number: String,
+    intake_date: [u32; 3],
+}
+
+// One physical lot of a product as it is stored right now
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Lot {
+    pub item_id: u32,
+    pub sku: String,
+    pub lot_number: String,
+    pub quantity: u32,
+    pub expiration_date: Option<[u32; 3]>,
+    pub intake_date: [u32; 3],
+    pub positions: Vec<Position>,
+}
+
+impl Display for Lot {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let [day, month, year] = self.intake_date;
+        write!(
+            f,
+            "lot {} (item {}): quantity {}, received {:02}-{:02}-{}",
+            self.lot_number, self.item_id, self.quantity, day, month, year
+        )?;
+        if let Some([day, month, year]) = self.expiration_date {
+            write!(f, ", expires {:02}-{:02}-{}", day, month, year)?;
+        }
+        if let Some(pos) = self.positions.first() {
+            write!(f, ", at {}", pos)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Catalog {
+    products: BTreeMap<String, Product>, // by sku
+    lots: HashMap<u32, LotInfo>,         // by item id
+}
+
+impl Catalog {
+    pub fn product(&self, sku: &str) -> Option<&Product> {
+        self.products.get(sku)
+    }
+
+    // called when the item leaves the warehouse
+    pub fn forget_lot(&mut self, item_id: u32) {
+        self.lots.remove(&item_id);
+    }
+
+    // when the lot of a stored item was received, None if it was not received as a lot
+    pub fn intake_date(&self, item_id: u32) -> Option<[u32; 3]> {
+        self.lots.get(&item_id).map(|info| info.intake_date)
+    }
+
+    // the lot record and product of a stored item, None if it was not received as a lot
+    pub(crate) fn lot_record(&self, item_id: u32) -> Option<(LotInfo, Product)> {
+        let info = self.lots.get(&item_id)?;
+        let product = self.products.get(&info.sku)?;
+        Some((info.clone(), product.clone()))
+    }
+
+    // takes over a lot record from another warehouse's catalog, registering its product
+    // here as well if this catalog does not know the sku yet
+    pub(crate) fn adopt_lot(&mut self, item_id: u32, info: LotInfo, product: Product) {
+        self.products.entry(product.sku.clone()).or_insert(product);
+        self.lots.insert(item_id, info);
+    }
+}
+
+impl Placement {
+    pub fn catalog(&self) -> &Catalog {
+        &self.catalog
+    }
+
+    pub fn register_product(&mut self, product: Product) -> Result<(), MyError> {
+        if self.catalog.products.contains_key(&product.sku) {
+            return Err(MyError::DuplicateSku(product.sku));
+        }
+        self.catalog.products.insert(product.sku.clone(), product);
+        Ok(())
+    }
+
+    // Stores a new lot of a registered product as its own item, so several lots of one sku
+    // can sit in different places with different expiration dates. Without an item id one
+    // is generated. Returns the id the lot is stored under.
+    pub fn receive_lot(
+        &mut self,
+        sku: &str,
+        lot_number: &str,
+        item_id: Option<u32>,
+        quantity: u32,
+        expiration_date: Option<[u32; 3]>,
+        intake_date: [u32; 3],
+    ) -> Result<u32, MyError> {
+        let product = self
+            .catalog
+            .product(sku)
+            .ok_or_else(|| MyError::UnknownSku(sku.to_string()))?;
+
+        let quality = match (&product.default_quality, expiration_date) {
+            (Quality::Fragile { row, .. }, Some(expiration_date)) => Quality::Fragile {
+                expiration_date,
+                row: *row,
+            },
+            (Quality::Fragile { .. }, None) => {
+                return Err(MyError::WrongOption(format!(
+                    "{} is perishable, its lots need an expiration date",
+                    sku
+                )));
+            }
+            (quality, _) => quality.clone(),
+        };
+
+        let item_id = self.add_new_item(NewItem {
+            id: item_id,
+            name: product.name.clone(),
+            quantity,
+            quality,
+        })?;
+
+        self.catalog.lots.insert(
+            item_id,
+            LotInfo {
+                sku: sku.to_string(),
+                lot_number: lot_number.to_string(),
+                intake_date,
+            },
+        );
+        Ok(item_id)
+    }
+
+    // every lot of the sku still in stock, oldest intake first
+    pub fn lots(&self, sku: &str) -> Vec<Lot> {
+        let mut lots: Vec<Lot> = self
+            .catalog
+            .lots
+            .iter()
+            .filter(|(_, info)| info.sku == sku)
+            .filter_map(|(id, info)| {
+                let item = self.storage.items.get(id)?;
+                let expiration_date = match &item.quality {
+                    Quality::Fragile {
+                        expiration_date, ..
+                    } => Some(*expiration_date),
+                    _ => None,
+                };
+                Some(Lot {
+                    item_id: *id,
+                    sku: info.sku.clone(),
+                    lot_number: info.lot_number.clone(),
+                    quantity: item.quantity,
+                    expiration_date,
+                    intake_date: info.intake_date,
+                    positions: self.position_map.get(id).cloned().unwrap_or_default(),
+                })
+            })
+            .collect();
+        lots.sort_by_key(|lot| (date_key(lot.intake_date), lot.item_id));
+        lots
+    }
+
+    // sku -> quantity in stock over all its lots, registered products without stock count as 0
+    pub fn sku_totals(&self) -> BTreeMap<String, u32> {
+        let mut totals: BTreeMap<String, u32> = self
+            .catalog
+            .products
+            .keys()
+            .map(|sku| (sku.clone(), 0))
+            .collect();
+        for (id, info) in &self.catalog.lots {
+            if let Some(item) = self.storage.items.get(id) {
+                *totals.entry(info.sku.clone()).or_insert(0) += item.quantity;
+            }
+        }
+        totals
+    }
+}
@@ -0,0 +1,74 @@
+use crate::orders::Pick;
+use crate::query::date_key;
+use crate::{MyError, Placement, Quality};
+
+impl Placement {
+    // Ids of every item called `name`, in the order stock should leave the warehouse:
+    // perishables first, earliest expiration date first (FEFO), then everything else
+    // in the order it came in (FIFO). Lots go by the date they were received, items
+    // without one after them; the order they were stored here only breaks ties.
+    pub fn fefo_ids(&self, name: &str) -> Vec<u32> {
+        let mut ids: Vec<u32> = self
+            .storage
+            .items
+            .values()
+            .filter(|item| item.name == name)
+            .map(|item| item.id)
+            .collect();
+        ids.sort_by_key(|id| {
+            let expiration = match &self.storage.items[id].quality {
+                Quality::Fragile {
+                    expiration_date, ..
+                } => Some(date_key(*expiration_date)),
+                _ => None,
+            };
+            let received = self.catalog.intake_date(*id).map(date_key);
+            let intake = self.intake.get(id).copied().unwrap_or(u64::MAX);
+            // None sorts before Some, so flip it to put perishables and dated lots first
+            (expiration.is_none(), expiration, received.is_none(), received, intake, *id)
+        });
+        ids
+    }
+
+    // Takes `quantity` of the product out of stock, drawing from the lot that expires first
+    // and moving on to the next one when it runs out. Stock reserved by open orders is left
+    // alone, and nothing is taken if there is not enough.
+    pub fn consume(&mut self, name: &str, quantity: u32) -> Result<Vec<Pick>, MyError> {
+        let ids = self.fefo_ids(name);
+        let available: u32 = ids.iter().map(|id| self.available_quantity(*id)).sum();
+        if available < quantity {
+            return Err(MyError::InsufficientStock {
+                name: name.to_string(),
+                requested: quantity,
+                available,
+            });
+        }
+
+        let mut picks = Vec::new();
+        let mut missing = quantity;
+        for id in ids {
+            if missing == 0 {
+                break;
+            }
+            let take = self.available_quantity(id).min(missing);
+            if take == 0 {
+                continue;
+            }
+            missing -= take;
+            picks.push(Pick {
+                item_id: id,
+                name: name.to_string(),
+                quantity: take,
+                position: self.position_map.get(&id).and_then(|v| v.first().copied()),
+            });
+
+            let stored = self.storage.items[&id].quantity;
+            if take == stored {
+                self.remove_item(id)?;
+            } else {
+                self.set_quantity(id, stored - take);
+            }
+        }
+        Ok(picks)
+    }
+}
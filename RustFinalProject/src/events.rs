@@ -0,0 +1,56 @@
+use crate::{Item, Position, Rejection};
+use std::fmt::Debug;
+
+// Listens to what happens in a Placement, registered with Placement::add_observer.
+// Every hook does nothing by default, so a listener only implements what it cares about.
+// Hooks get `&self`, so listeners that count or buffer keep that state behind a Mutex
+// or atomics.
+pub trait Observer: Debug + Send + Sync {
+    // stored at `positions`, by add_item or anything built on it
+    fn item_added(&self, _item: &Item, _positions: &[Position]) {}
+
+    // taken out of the placement, the item as it was last stored
+    fn item_removed(&self, _item: &Item) {}
+
+    fn blocked_by_filter(&self, _item: &Item, _rejection: &Rejection) {}
+
+    // the item could not be stored: the strategy found no position, or the position it
+    // found or the one reserved for it does not suit the item
+    fn allocation_failed(&self, _item: &Item) {}
+
+    // once per item, the first time Placement::notify_expired for `date` finds it expired
+    fn item_expired(&self, _item: &Item, _date: [u32; 3]) {}
+}
+
+// Writes one line per event to stderr, e.g. "[supermarket] added 7 milk at (0, 0, 9)"
+#[derive(Debug, Clone)]
+pub struct EventLog {
+    pub label: String,
+}
+
+impl Observer for EventLog {
+    fn item_added(&self, item: &Item, positions: &[Position]) {
+        let first = positions.first().map_or("no position".to_string(), |pos| pos.to_string());
+        eprintln!("[{}] added {} {} at {}", self.label, item.id, item.name, first);
+    }
+
+    fn item_removed(&self, item: &Item) {
+        eprintln!("[{}] removed {} {}", self.label, item.id, item.name);
+    }
+
+    fn blocked_by_filter(&self, item: &Item, rejection: &Rejection) {
+        eprintln!("[{}] blocked {} {}: {}", self.label, item.id, item.name, rejection);
+    }
+
+    fn allocation_failed(&self, item: &Item) {
+        eprintln!("[{}] no position for {} {}", self.label, item.id, item.name);
+    }
+
+    fn item_expired(&self, item: &Item, date: [u32; 3]) {
+        let [day, month, year] = date;
+        eprintln!(
+            "[{}] {} {} expired by {:02}-{:02}-{}",
+            self.label, item.id, item.name, day, month, year
+        );
+    }
+}
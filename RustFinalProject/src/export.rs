@@ -0,0 +1,255 @@
+use crate::query::day_number;
+use crate::{Item, MyError, Placement, Position, Quality};
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write;
+use std::path::Path;
+
+const CELL_WIDTH: u32 = 28;
+const CELL_HEIGHT: u32 = 20;
+const LABEL_WIDTH: u32 = 70;
+const ROW_GAP: u32 = 36;
+
+const FREE: &str = "#e6e6e6";
+const RESERVED: &str = "#aed6f1"; // held for an announced delivery
+
+// What the cells are coloured by
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum HeatmapMode {
+    Occupancy,
+    Quality,
+    Expiry, // days left until the expiration date, relative to `today`
+}
+
+impl HeatmapMode {
+    const ALL: [HeatmapMode; 3] = [
+        HeatmapMode::Occupancy,
+        HeatmapMode::Quality,
+        HeatmapMode::Expiry,
+    ];
+
+    fn title(&self) -> &'static str {
+        match self {
+            HeatmapMode::Occupancy => "Occupancy",
+            HeatmapMode::Quality => "Quality",
+            HeatmapMode::Expiry => "Days to expiry",
+        }
+    }
+
+    fn legend(&self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            HeatmapMode::Occupancy => &[
+                (FREE, "free"),
+                (RESERVED, "reserved"),
+                ("#c0392b", "item"),
+                ("#e59866", "oversized span"),
+            ],
+            HeatmapMode::Quality => &[
+                (FREE, "free"),
+                (RESERVED, "reserved"),
+                ("#5b9bd5", "normal"),
+                ("#f0ad4e", "fragile"),
+                ("#9b59b6", "oversized"),
+                ("#1abc9c", "refrigerated"),
+                ("#2c3e50", "hazardous"),
+            ],
+            HeatmapMode::Expiry => &[
+                (FREE, "free"),
+                (RESERVED, "reserved"),
+                ("#7f8c8d", "does not expire"),
+                ("#641e16", "expired"),
+                ("#e74c3c", "7 days or less"),
+                ("#f39c12", "30 days or less"),
+                ("#27ae60", "more than 30 days"),
+            ],
+        }
+    }
+
+    // (item, is the first cell of the item) -> fill colour
+    fn colour(&self, cell: Option<(&Item, bool)>, today: [u32; 3]) -> &'static str {
+        let Some((item, first)) = cell else {
+            return FREE;
+        };
+        match self {
+            HeatmapMode::Occupancy if first => "#c0392b",
+            HeatmapMode::Occupancy => "#e59866",
+            HeatmapMode::Quality => match item.quality {
+                Quality::Normal => "#5b9bd5",
+                Quality::Fragile { .. } => "#f0ad4e",
+                Quality::Oversized { .. } => "#9b59b6",
+                Quality::Refrigerated { .. } => "#1abc9c",
+                Quality::Hazardous { .. } => "#2c3e50",
+            },
+            HeatmapMode::Expiry => match days_to_expiry(item, today) {
+                None => "#7f8c8d",
+                Some(days) if days <= 0 => "#641e16",
+                Some(days) if days <= 7 => "#e74c3c",
+                Some(days) if days <= 30 => "#f39c12",
+                Some(_) => "#27ae60",
+            },
+        }
+    }
+}
+
+fn days_to_expiry(item: &Item, today: [u32; 3]) -> Option<i64> {
+    match item.quality {
+        Quality::Fragile {
+            expiration_date, ..
+        } => Some(day_number(expiration_date) - day_number(today)),
+        _ => None,
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+impl Placement {
+    // position -> (item, is the first cell of the item), continuation cells included
+    fn cell_items(&self) -> HashMap<Position, (&Item, bool)> {
+        let mut cells = HashMap::new();
+        for (id, positions) in &self.position_map {
+            if let Some(item) = self.storage.items.get(id) {
+                for (index, pos) in positions.iter().enumerate() {
+                    cells.insert(*pos, (item, index == 0));
+                }
+            }
+        }
+        cells
+    }
+
+    // One self-contained SVG image: every row is a block of shelves (lines) x zones (columns),
+    // hovering a cell shows the position and the item stored there.
+    pub fn heatmap_svg(&self, mode: HeatmapMode, today: [u32; 3]) -> String {
+        let cells = self.cell_items();
+        let rows = self.rows();
+        let shelves: BTreeSet<u32> = (0..self.layout.shelves).collect();
+        let zones: BTreeSet<u32> = (0..self.layout.zones).collect();
+
+        let row_height = shelves.len() as u32 * CELL_HEIGHT + ROW_GAP;
+        let legend_height = 30;
+        let width = LABEL_WIDTH + (zones.len() as u32).max(12) * CELL_WIDTH + 10;
+        let height = legend_height + rows.len() as u32 * row_height;
+
+        let mut svg = String::new();
+        // writing into a String cannot fail
+        let _ = writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" font-family="monospace" font-size="11">"#
+        );
+
+        let mut x = 0;
+        for (colour, label) in mode.legend() {
+            let _ = writeln!(
+                svg,
+                r##"<rect x="{x}" y="4" width="14" height="14" fill="{colour}" stroke="#999"/><text x="{}" y="15">{label}</text>"##,
+                x + 18
+            );
+            x += 24 + label.len() as u32 * 7;
+        }
+
+        for (row_index, row) in rows.iter().enumerate() {
+            let top = legend_height + row_index as u32 * row_height;
+            let _ = writeln!(
+                svg,
+                r#"<text x="0" y="{}" font-weight="bold">Row {row}</text>"#,
+                top + 14
+            );
+            for (shelf_index, shelf) in shelves.iter().enumerate() {
+                let y = top + 20 + shelf_index as u32 * CELL_HEIGHT;
+                let _ = writeln!(
+                    svg,
+                    r#"<text x="0" y="{}">shelf {shelf}</text>"#,
+                    y + 14
+                );
+                for (zone_index, zone) in zones.iter().enumerate() {
+                    let pos = Position::new(*row, *shelf, *zone);
+                    if !self.storage.contains(&pos) {
+                        continue;
+                    }
+                    let cell = cells.get(&pos).copied();
+                    let x = LABEL_WIDTH + zone_index as u32 * CELL_WIDTH;
+                    let reservation = self.storage.reservation(&pos);
+                    let colour = match (cell, reservation) {
+                        (None, Some(_)) => RESERVED,
+                        _ => mode.colour(cell, today),
+                    };
+
+                    let tooltip = match (cell, reservation) {
+                        (Some((item, _)), _) => {
+                            let mut text = format!(
+                                "{pos}\nid: {}\nname: {}\nquantity: {}\nquality: {}",
+                                item.id, item.name, item.quantity, item.quality
+                            );
+                            if let Some(days) = days_to_expiry(item, today) {
+                                let _ = write!(text, "\ndays to expiry: {days}");
+                            }
+                            text
+                        }
+                        (None, Some(asn)) => match self.inbound.get(asn) {
+                            Some(expected) => {
+                                format!("{pos}\nreserved for delivery {asn}\nexpected: {}", expected.name)
+                            }
+                            None => format!("{pos}\nreserved for delivery {asn}"),
+                        },
+                        (None, None) => format!("{pos}\nfree"),
+                    };
+
+                    let _ = writeln!(
+                        svg,
+                        r##"<rect x="{x}" y="{y}" width="{}" height="{}" fill="{colour}" stroke="#fff"><title>{}</title></rect>"##,
+                        CELL_WIDTH,
+                        CELL_HEIGHT,
+                        escape(&tooltip)
+                    );
+                }
+            }
+        }
+
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    // HTML page with one heatmap per mode, nothing external to load
+    pub fn heatmap_html(&self, today: [u32; 3]) -> String {
+        let [day, month, year] = today;
+        let mut html = String::new();
+        let _ = writeln!(
+            html,
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Warehouse heatmap</title>\n\
+             <style>body {{ font-family: sans-serif; }} section {{ display: inline-block; vertical-align: top; margin-right: 24px; }}</style>\n\
+             </head>\n<body>\n<h1>Warehouse heatmap</h1>\n<p>Positions used: {} - generated for {day:02}-{month:02}-{year}, hover a cell for details</p>",
+            escape(&self.stats().overall.to_string())
+        );
+        for mode in HeatmapMode::ALL {
+            let _ = writeln!(
+                html,
+                "<section>\n<h2>{}</h2>\n{}</section>",
+                mode.title(),
+                self.heatmap_svg(mode, today)
+            );
+        }
+        html.push_str("</body>\n</html>\n");
+        html
+    }
+
+    // .svg paths get a single SVG for `mode`, anything else gets the HTML page with every mode
+    pub fn export_heatmap(
+        &self,
+        path: &Path,
+        mode: HeatmapMode,
+        today: [u32; 3],
+    ) -> Result<(), MyError> {
+        let is_svg = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("svg"));
+        let content = if is_svg {
+            self.heatmap_svg(mode, today)
+        } else {
+            self.heatmap_html(today)
+        };
+        std::fs::write(path, content).map_err(MyError::IOError)
+    }
+}
@@ -0,0 +1,125 @@
+use crate::{Placement, Quality};
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::{Display, Formatter};
+
+// What a single cell of the grid shows
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum Cell {
+    Free,
+    Item { id: u32, fragile: bool },
+    Continuation, // later zones of an Oversized item
+    Reserved,     // held for an inbound delivery
+}
+
+// ASCII picture of one row: one line per shelf, one column per zone.
+//
+// Row 0
+//            0   1   2   3   4   5
+// shelf 0 |  1   2  === ===  5*  .  |
+// shelf 1 |  .   .   .   .   .   .  |
+//
+// Built with Placement::render_row, printed with Display.
+pub struct RowGrid<'a> {
+    placement: &'a Placement,
+    row: u32,
+}
+
+impl Placement {
+    pub fn render_row(&self, row: u32) -> RowGrid<'_> {
+        RowGrid {
+            placement: self,
+            row,
+        }
+    }
+
+    // every row number that exists in the layout, in order
+    pub fn rows(&self) -> BTreeSet<u32> {
+        (0..self.layout.rows).collect()
+    }
+
+    // (shelf, zone) -> what is there, for one row
+    fn row_cells(&self, row: u32) -> HashMap<(u32, u32), Cell> {
+        let mut cells: HashMap<(u32, u32), Cell> = self
+            .storage
+            .cells()
+            .filter(|(pos, _)| pos.row == row)
+            .map(|(pos, _)| ((pos.shelf, pos.zone), Cell::Free))
+            .collect();
+        for (pos, _) in self.storage.reserved_cells().filter(|(pos, _)| pos.row == row) {
+            cells.insert((pos.shelf, pos.zone), Cell::Reserved);
+        }
+
+        for (id, positions) in &self.position_map {
+            let fragile = matches!(
+                self.storage.items.get(id).map(|item| &item.quality),
+                Some(Quality::Fragile { .. })
+            );
+            // the first stored position is where the item starts, the rest is its span
+            for (index, pos) in positions.iter().enumerate().filter(|(_, p)| p.row == row) {
+                let cell = if index == 0 {
+                    Cell::Item { id: *id, fragile }
+                } else {
+                    Cell::Continuation
+                };
+                cells.insert((pos.shelf, pos.zone), cell);
+            }
+        }
+        cells
+    }
+}
+
+impl Display for RowGrid<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let cells = self.placement.row_cells(self.row);
+        match self.placement.layout.row_temperatures.get(&self.row) {
+            Some(zone) => writeln!(f, "Row {} ({})", self.row, zone)?,
+            None => writeln!(f, "Row {}", self.row)?,
+        }
+        if cells.is_empty() {
+            return writeln!(f, "  (no such row)");
+        }
+
+        let shelves: BTreeSet<u32> = cells.keys().map(|(shelf, _)| *shelf).collect();
+        let zones: BTreeSet<u32> = cells.keys().map(|(_, zone)| *zone).collect();
+
+        // wide enough for the longest id plus the fragile marker
+        let width = cells
+            .values()
+            .filter_map(|cell| match cell {
+                Cell::Item { id, .. } => Some(id.to_string().len()),
+                _ => None,
+            })
+            .chain(zones.iter().map(|zone| zone.to_string().len()))
+            .max()
+            .unwrap_or(1)
+            .max(2)
+            + 1;
+        let label = format!("shelf {}", shelves.last().unwrap_or(&0)).len();
+
+        write!(f, "{:label$}  ", "")?;
+        for zone in &zones {
+            write!(f, " {:>w$} ", zone, w = width - 1)?;
+        }
+        writeln!(f)?;
+
+        for shelf in &shelves {
+            write!(f, "{:<label$} |", format!("shelf {}", shelf))?;
+            for zone in &zones {
+                let text = match cells.get(&(*shelf, *zone)) {
+                    Some(Cell::Free) | None => format!("{:>w$} ", ".", w = width - 1),
+                    Some(Cell::Item { id, fragile }) => {
+                        format!("{:>w$}{}", id, if *fragile { "*" } else { " " }, w = width - 1)
+                    }
+                    Some(Cell::Continuation) => "=".repeat(width),
+                    Some(Cell::Reserved) => format!("{:>w$} ", "r", w = width - 1),
+                };
+                write!(f, " {}", text)?;
+            }
+            writeln!(f, " |")?;
+        }
+        Ok(())
+    }
+}
+
+// legend shared by every rendered row
+pub const GRID_LEGEND: &str = ". free | <id> item | === oversized span | * fragile | r reserved";
@@ -0,0 +1,318 @@
+use crate::query::{QualityKind, date_key};
+use crate::{Item, MyError, NewItem, Placement, Position, Quality, check_span};
+use itertools::Itertools;
+use std::fmt::{Display, Formatter};
+
+// Cells held for an announced delivery (ASN, advance shipping notice) until it arrives
+// or the reservation runs out
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Reservation {
+    pub asn: String,
+    pub name: String, // name of the item that is expected
+    pub quality: Quality,
+    pub positions: Vec<Position>,
+    pub expires: [u32; 3], // [day, month, year], the last day the cells are held
+}
+
+impl Display for Reservation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let [day, month, year] = self.expires;
+        write!(
+            f,
+            "{}: {} at {} until {:02}-{:02}-{}",
+            self.asn,
+            self.name,
+            self.positions.iter().join(", "),
+            day,
+            month,
+            year
+        )
+    }
+}
+
+impl Placement {
+    // Holds cells for delivery `asn`, chosen by the strategy as if the item were stored now.
+    // Until the delivery arrives no strategy hands those cells to anything else.
+    pub fn reserve(
+        &mut self,
+        asn: &str,
+        name: &str,
+        quality: Quality,
+        expires: [u32; 3],
+    ) -> Result<Vec<Position>, MyError> {
+        if self.inbound.contains_key(asn) {
+            return Err(MyError::DuplicateReservation(asn.to_string()));
+        }
+        // stands in for the expected item, it never reaches the item table
+        let expected = Item {
+            id: self.next_free_id(),
+            name: name.to_string(),
+            quantity: 0,
+            quality,
+        };
+        check_span(&expected)?;
+        if let Err(rejection) = self.check_filters(&expected) {
+            return Err(MyError::BlockedByFilter(expected, Box::new(rejection)));
+        }
+        let Some(position) = self
+            .allocation_strategy
+            .allocate(&expected, &self.storage, &self.layout)
+        else {
+            return Err(MyError::FailedAllocation(expected));
+        };
+        self.check_fit(&expected, &position)?;
+        self.check_segregation(&expected, &position)?;
+
+        let span = expected.quality.span();
+        let positions: Vec<Position> = (position.zone..position.zone + span)
+            .map(|zone| Position::new(position.row, position.shelf, zone))
+            .collect();
+        let reservation = Reservation {
+            asn: asn.to_string(),
+            name: expected.name,
+            quality: expected.quality,
+            positions: positions.clone(),
+            expires,
+        };
+        self.hold(reservation);
+        Ok(positions)
+    }
+
+    // every open reservation, by ASN
+    pub fn reservations(&self) -> impl Iterator<Item = &Reservation> + '_ {
+        self.inbound.values()
+    }
+
+    // the delivery will not come, its cells are free again
+    pub fn cancel_reservation(&mut self, asn: &str) -> Result<Reservation, MyError> {
+        self.release(asn)
+            .ok_or_else(|| MyError::UnknownReservation(asn.to_string()))
+    }
+
+    // Drops every reservation whose last day is before `today` and returns them
+    pub fn expire_reservations(&mut self, today: [u32; 3]) -> Vec<Reservation> {
+        let lapsed: Vec<String> = self
+            .inbound
+            .values()
+            .filter(|reservation| date_key(reservation.expires) < date_key(today))
+            .map(|reservation| reservation.asn.clone())
+            .collect();
+        lapsed
+            .iter()
+            .filter_map(|asn| self.release(asn))
+            .collect()
+    }
+
+    // Stores the item of delivery `asn` in the cells held for it, picking an id if it has
+    // none. Returns the id it was stored under.
+    pub fn receive_delivery(&mut self, asn: &str, new: NewItem) -> Result<u32, MyError> {
+        let item = self.with_id(new);
+        let reservation = self
+            .inbound
+            .get(asn)
+            .ok_or_else(|| MyError::UnknownReservation(asn.to_string()))?;
+        if let Some(mismatch) = mismatch(reservation, &item) {
+            return Err(MyError::WrongDelivery(asn.to_string(), mismatch));
+        }
+        let id = item.id;
+        self.place(item, Some(asn.to_string()))?;
+        Ok(id)
+    }
+
+    // the delivery an item stands for when it comes in through add_item: same name, quality
+    // and span, and its cells still suit the item; the one running out first
+    pub(crate) fn matching_reservation(&mut self, item: &Item) -> Option<String> {
+        let candidates: Vec<String> = self
+            .inbound
+            .values()
+            .filter(|reservation| mismatch(reservation, item).is_none())
+            .sorted_by_key(|reservation| date_key(reservation.expires))
+            .map(|reservation| reservation.asn.clone())
+            .collect();
+        candidates
+            .into_iter()
+            .find(|asn| self.reservation_fits(asn, item))
+    }
+
+    // would claim_reservation succeed; the reservation is left as it was either way
+    fn reservation_fits(&mut self, asn: &str, item: &Item) -> bool {
+        let Some(reservation) = self.release(asn) else {
+            return false;
+        };
+        let position = reservation.positions[0];
+        let fits = self
+            .check_fit(item, &position)
+            .and_then(|_| self.check_segregation(item, &position))
+            .is_ok();
+        self.hold(reservation);
+        fits
+    }
+
+    // Turns the reservation into the item's position: the cells are released and checked
+    // like any other placement. If the item does not fit, the reservation stays as it was.
+    pub(crate) fn claim_reservation(&mut self, asn: &str, item: &Item) -> Result<Position, MyError> {
+        let reservation = self
+            .release(asn)
+            .ok_or_else(|| MyError::UnknownReservation(asn.to_string()))?;
+        let position = reservation.positions[0];
+        let result = self
+            .check_fit(item, &position)
+            .and_then(|_| self.check_segregation(item, &position));
+        match result {
+            Ok(()) => Ok(position),
+            Err(err) => {
+                self.hold(reservation);
+                Err(err)
+            }
+        }
+    }
+
+    fn hold(&mut self, reservation: Reservation) {
+        for pos in &reservation.positions {
+            self.storage.set_reserved(pos, Some(reservation.asn.clone()));
+            if let Quality::Hazardous { class } = reservation.quality {
+                self.storage.set_reserved_hazard(pos, class);
+            }
+        }
+        self.inbound.insert(reservation.asn.clone(), reservation);
+    }
+
+    fn release(&mut self, asn: &str) -> Option<Reservation> {
+        let reservation = self.inbound.remove(asn)?;
+        for pos in &reservation.positions {
+            self.storage.set_reserved(pos, None);
+        }
+        Some(reservation)
+    }
+}
+
+// why `item` is not the delivery `reservation` waits for, None if it is
+fn mismatch(reservation: &Reservation, item: &Item) -> Option<String> {
+    let (expected, actual) = (QualityKind::of(&reservation.quality), QualityKind::of(&item.quality));
+    if reservation.name != item.name {
+        Some(format!("reserved for {}, not {}", reservation.name, item.name))
+    } else if expected != actual {
+        Some(format!("reserved for quality {}, not {}", expected, actual))
+    } else if reservation.quality.span() != item.quality.span() {
+        Some(format!(
+            "reserved {} zones, the item needs {}",
+            reservation.quality.span(),
+            item.quality.span()
+        ))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Layout, TemperatureZone};
+
+    fn item(id: u32, name: &str, quality: Quality) -> Item {
+        Item {
+            id,
+            name: name.to_string(),
+            quantity: 1,
+            quality,
+        }
+    }
+
+    fn positions(placement: &Placement, id: u32) -> Vec<(u32, u32, u32)> {
+        placement.position_map[&id].iter().map(|pos| pos.as_tuple()).collect()
+    }
+
+    #[test]
+    fn add_item_leaves_a_reservation_of_another_quality_alone() {
+        let mut placement = Placement::with_layout(Layout::new(10, 1, 5));
+        placement.set_temperature_zone(9, None, Some(TemperatureZone { min: 2, max: 6 }));
+        let cold = Quality::Refrigerated {
+            min_temp: 0,
+            max_temp: 8,
+        };
+        let held = placement.reserve("M", "Milk", cold, [1, 1, 2030]).unwrap();
+        assert_eq!(held, vec![Position::new(9, 0, 0)]);
+
+        placement.add_item(item(1, "Milk", Quality::Normal)).unwrap();
+        assert_eq!(positions(&placement, 1), vec![(0, 0, 0)]);
+        assert_eq!(placement.reservations().count(), 1);
+        assert_eq!(placement.storage.reservation(&Position::new(9, 0, 0)), Some("M"));
+    }
+
+    #[test]
+    fn add_item_falls_back_to_the_strategy_when_the_reservation_does_not_suit_it() {
+        let mut placement = Placement::with_layout(Layout::new(4, 1, 1));
+        placement.add_item(item(1, "Bread", Quality::Normal)).unwrap();
+        placement.add_item(item(2, "Bread", Quality::Normal)).unwrap();
+        let anywhere = Quality::Fragile {
+            expiration_date: [1, 1, 2030],
+            row: 10,
+        };
+        let held = placement.reserve("M", "Milk", anywhere, [1, 1, 2030]).unwrap();
+        assert_eq!(held, vec![Position::new(2, 0, 0)]);
+        placement.remove_item(1).unwrap();
+        placement.remove_item(2).unwrap();
+
+        // same name, quality and span, but row 2 is too high for this one
+        let low = Quality::Fragile {
+            expiration_date: [1, 1, 2030],
+            row: 2,
+        };
+        placement.add_item(item(3, "Milk", low)).unwrap();
+        assert_eq!(positions(&placement, 3), vec![(0, 0, 0)]);
+        assert_eq!(placement.reservations().count(), 1);
+    }
+
+    #[test]
+    fn add_item_takes_over_a_matching_reservation() {
+        let mut placement = Placement::with_layout(Layout::new(1, 1, 5));
+        placement.add_item(item(1, "Bread", Quality::Normal)).unwrap();
+        placement.reserve("M", "Milk", Quality::Normal, [1, 1, 2030]).unwrap();
+        placement.remove_item(1).unwrap();
+
+        placement.add_item(item(2, "Milk", Quality::Normal)).unwrap();
+        assert_eq!(positions(&placement, 2), vec![(0, 0, 1)]);
+        assert_eq!(placement.reservations().count(), 0);
+    }
+
+    #[test]
+    fn hazardous_reservations_keep_their_segregation_distance() {
+        let mut placement = Placement::with_layout(Layout::new(1, 1, 10));
+        placement.set_segregation(1, 2, 3);
+        let held = placement
+            .reserve("H", "Acid", Quality::Hazardous { class: 1 }, [1, 1, 2030])
+            .unwrap();
+        assert_eq!(held, vec![Position::new(0, 0, 0)]);
+
+        placement.add_item(item(1, "Lye", Quality::Hazardous { class: 2 })).unwrap();
+        assert_eq!(positions(&placement, 1), vec![(0, 0, 3)]);
+        let err = placement.move_item(1, Position::new(0, 0, 1)).unwrap_err();
+        assert_eq!(err.kind(), "InvalidPosition");
+
+        let delivered = NewItem {
+            id: None,
+            name: "Acid".to_string(),
+            quantity: 1,
+            quality: Quality::Hazardous { class: 1 },
+        };
+        let id = placement.receive_delivery("H", delivered).unwrap();
+        assert_eq!(positions(&placement, id), vec![(0, 0, 0)]);
+    }
+
+    #[test]
+    fn receive_delivery_rejects_an_item_of_another_quality() {
+        let mut placement = Placement::with_layout(Layout::new(1, 1, 5));
+        placement.reserve("M", "Milk", Quality::Normal, [1, 1, 2030]).unwrap();
+        let delivered = NewItem {
+            id: None,
+            name: "Milk".to_string(),
+            quantity: 1,
+            quality: Quality::Oversized {
+                continuous_zones: 2,
+            },
+        };
+        let err = placement.receive_delivery("M", delivered).unwrap_err();
+        assert_eq!(err.kind(), "WrongDelivery");
+        assert_eq!(placement.reservations().count(), 1);
+    }
+}
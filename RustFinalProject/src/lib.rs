@@ -0,0 +1,1029 @@
+pub mod catalog;
+pub mod consume;
+pub mod events;
+pub mod export;
+pub mod grid;
+pub mod inbound;
+pub mod machine;
+pub mod orders;
+pub mod query;
+pub mod replenish;
+pub mod route;
+pub mod server;
+pub mod shared;
+pub mod simulate;
+pub mod stats;
+pub mod storage;
+pub mod validate;
+pub mod warehouses;
+
+use itertools::{Itertools, iproduct};
+use catalog::Catalog;
+use events::Observer;
+use inbound::Reservation;
+use orders::OrderBook;
+use replenish::ReorderRule;
+use serde::{Deserialize, Serialize};
+use storage::Storage;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::{Debug, Display, Formatter};
+use std::num::ParseIntError;
+use thiserror::Error;
+
+pub const MAXPOSITION: u32 = 10;
+// the cell grid is allocated up front, layouts with more positions are refused
+pub const MAXCELLS: usize = 10_000_000;
+
+#[derive(Error, Debug)]
+pub enum MyError {
+    // failed to add
+    #[error("Could not add item {0}")]
+    FailedAdd(Item),
+    // id already taken by another item
+    #[error("An item with Id {0} already exists")]
+    DuplicateId(u32),
+    // failed to remove
+    #[error("Could not remove item with Id {0}")]
+    FailedRemove(u32),
+    // got filtered
+    #[error("The item {0} was blocked by {1}")]
+    BlockedByFilter(Item, Box<Rejection>),
+    // not enough unreserved stock of a product
+    #[error("Only {available} of {name} available, {requested} requested")]
+    InsufficientStock {
+        name: String,
+        requested: u32,
+        available: u32,
+    },
+    // no product registered under this sku
+    #[error("No product with SKU {0}")]
+    UnknownSku(String),
+    // sku already registered
+    #[error("Product {0} already exists")]
+    DuplicateSku(String),
+    // no item with this id
+    #[error("No item with Id {0}")]
+    UnknownId(u32),
+    // no item with this name
+    #[error("No item named {0}")]
+    UnknownName(String),
+    // no open order or backorder with this id
+    #[error("No open order with Id {0}")]
+    UnknownOrder(u32),
+    // no warehouse with this name
+    #[error("No warehouse named {0}")]
+    UnknownWarehouse(String),
+    // warehouse name already in use
+    #[error("Warehouse {0} already exists")]
+    DuplicateWarehouse(String),
+    // open orders hold some of the item
+    #[error("Item with Id {0} is reserved by an open order")]
+    ItemReserved(u32),
+    // order id already in use
+    #[error("Order {0} already exists")]
+    DuplicateOrder(u32),
+    // hazardous item would be too close to another one
+    #[error("Item {id} must be at least {required} positions away from item {other} (would be {actual})")]
+    SegregationViolation {
+        id: u32,
+        other: u32,
+        required: u32,
+        actual: u32,
+    },
+    // position does not exist or does not suit the item
+    #[error("Item cannot go to {0}: {1}")]
+    InvalidPosition(Position, String),
+    // no reservation held for this delivery
+    #[error("No reservation for delivery {0}")]
+    UnknownReservation(String),
+    // delivery already has positions reserved
+    #[error("Delivery {0} is already reserved")]
+    DuplicateReservation(String),
+    // the item that arrived is not the one the delivery was announced with
+    #[error("Delivery {0} does not match its reservation: {1}")]
+    WrongDelivery(String, String),
+    // the item itself cannot be stored anywhere, e.g. an oversized item spanning no zones
+    #[error("Item {0} cannot be stored: {1}")]
+    InvalidItem(Item, String),
+    // failed to find an alloc
+    #[error("The allocator could not find a position for item {0}")]
+    FailedAllocation(Item),
+    // IO error
+    #[error("IO error: {0}")]
+    IOError(std::io::Error),
+    // Parse error
+    #[error("Parse int error: {0}")]
+    ParseIntError(ParseIntError),
+    //Invalid Error Format
+    #[error("Invalid Date Format: {0}")]
+    InvalidDateFormat(String),
+    //Wrong Option
+    #[error("Wrong Option: {0}")]
+    WrongOption(String),
+    // request from another program that cannot be understood
+    #[error("Invalid request: {0}")]
+    InvalidRequest(String),
+    // request body larger than the server accepts
+    #[error("Request body of {0} bytes is larger than the {1} allowed")]
+    RequestTooLarge(usize, usize),
+}
+
+impl MyError {
+    // stable name of the variant, for programs reading our errors
+    pub fn kind(&self) -> &'static str {
+        match self {
+            MyError::FailedAdd(_) => "FailedAdd",
+            MyError::DuplicateId(_) => "DuplicateId",
+            MyError::FailedRemove(_) => "FailedRemove",
+            MyError::BlockedByFilter(..) => "BlockedByFilter",
+            MyError::InsufficientStock { .. } => "InsufficientStock",
+            MyError::UnknownSku(_) => "UnknownSku",
+            MyError::DuplicateSku(_) => "DuplicateSku",
+            MyError::UnknownId(_) => "UnknownId",
+            MyError::UnknownName(_) => "UnknownName",
+            MyError::UnknownOrder(_) => "UnknownOrder",
+            MyError::UnknownWarehouse(_) => "UnknownWarehouse",
+            MyError::DuplicateWarehouse(_) => "DuplicateWarehouse",
+            MyError::ItemReserved(_) => "ItemReserved",
+            MyError::DuplicateOrder(_) => "DuplicateOrder",
+            MyError::SegregationViolation { .. } => "SegregationViolation",
+            MyError::InvalidPosition(..) => "InvalidPosition",
+            MyError::UnknownReservation(_) => "UnknownReservation",
+            MyError::DuplicateReservation(_) => "DuplicateReservation",
+            MyError::InvalidItem(..) => "InvalidItem",
+            MyError::WrongDelivery(..) => "WrongDelivery",
+            MyError::FailedAllocation(_) => "FailedAllocation",
+            MyError::IOError(_) => "IOError",
+            MyError::ParseIntError(_) => "ParseIntError",
+            MyError::InvalidDateFormat(_) => "InvalidDateFormat",
+            MyError::WrongOption(_) => "WrongOption",
+            MyError::InvalidRequest(_) => "InvalidRequest",
+            MyError::RequestTooLarge(..) => "RequestTooLarge",
+        }
+    }
+}
+
+// why a filter refused an item: which filter, which rule, and the value that broke it
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Rejection {
+    pub filter: String,
+    pub rule: String,
+    pub value: String,
+}
+
+impl Display for Rejection {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "filter {}: {} (got {})",
+            self.filter, self.rule, self.value
+        )
+    }
+}
+
+pub trait Filter: Debug + Send + Sync {
+    fn check_allowed(
+        &self,
+        item: &Item,
+        storage: &Storage,
+    ) -> Result<(), Rejection>;
+}
+
+pub trait Strategy: Debug + Send + Sync {
+    fn allocate(
+        &mut self,
+        item: &Item,
+        storage: &Storage,
+        layout: &Layout,
+    ) -> Option<Position>;
+}
+
+// temperature range a part of the warehouse is kept at, in °C
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct TemperatureZone {
+    pub min: i32,
+    pub max: i32,
+}
+
+impl Display for TemperatureZone {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}..{} °C", self.min, self.max)
+    }
+}
+
+// How far apart hazardous classes must be stored, e.g. oxidisers away from flammables.
+// Pairs without a rule (or with distance 0) may sit next to each other.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SegregationRules {
+    distances: HashMap<(u32, u32), u32>, // (lower class, higher class) -> min distance
+}
+
+impl SegregationRules {
+    pub fn set(&mut self, a: u32, b: u32, distance: u32) {
+        let key = (a.min(b), a.max(b));
+        if distance == 0 {
+            self.distances.remove(&key);
+        } else {
+            self.distances.insert(key, distance);
+        }
+    }
+
+    pub fn required(&self, a: u32, b: u32) -> u32 {
+        self.distances
+            .get(&(a.min(b), a.max(b)))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    // first hazard (position, id, class) that would be too close to a `class` item at `pos`,
+    // returned as (other id, required distance, actual distance). The id can be anything
+    // naming the other hazard, e.g. the ASN of a reservation.
+    pub fn violation<T: Clone>(
+        &self,
+        class: u32,
+        pos: &Position,
+        hazards: &[(Position, T, u32)],
+    ) -> Option<(T, u32, u32)> {
+        hazards.iter().find_map(|(other_pos, other_id, other_class)| {
+            let required = self.required(class, *other_class);
+            let actual = pos.distance(other_pos);
+            (actual < required).then(|| (other_id.clone(), required, actual))
+        })
+    }
+}
+
+// size of a warehouse, every position (row, shelf, zone) is below these bounds
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Layout {
+    pub rows: u32,
+    pub shelves: u32,
+    pub zones: u32,
+    // chilled areas: a whole row, or a single shelf which wins over its row
+    pub row_temperatures: HashMap<u32, TemperatureZone>,
+    pub shelf_temperatures: HashMap<(u32, u32), TemperatureZone>,
+    pub segregation: SegregationRules,
+}
+
+impl Default for Layout {
+    fn default() -> Layout {
+        Layout::new(MAXPOSITION, MAXPOSITION, MAXPOSITION)
+    }
+}
+
+impl Layout {
+    pub fn new(rows: u32, shelves: u32, zones: u32) -> Layout {
+        Layout {
+            rows,
+            shelves,
+            zones,
+            row_temperatures: HashMap::new(),
+            shelf_temperatures: HashMap::new(),
+            segregation: SegregationRules::default(),
+        }
+    }
+
+    // None means the position is not temperature controlled
+    pub fn temperature_at(&self, pos: &Position) -> Option<TemperatureZone> {
+        self.shelf_temperatures
+            .get(&(pos.row, pos.shelf))
+            .or_else(|| self.row_temperatures.get(&pos.row))
+            .copied()
+    }
+
+    // number of positions, None when there are more than MAXCELLS
+    pub fn cells(&self) -> Option<usize> {
+        (self.rows as usize)
+            .checked_mul(self.shelves as usize)?
+            .checked_mul(self.zones as usize)
+            .filter(|cells| *cells <= MAXCELLS)
+    }
+
+    // every position of the layout, row by row, shelf by shelf
+    pub fn positions(&self) -> impl Iterator<Item = Position> + use<> {
+        iproduct!(0..self.rows, 0..self.shelves, 0..self.zones).map(Position::from)
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct Position {
+    pub row: u32,
+    pub shelf: u32,
+    pub zone: u32,
+}
+
+impl Position {
+    pub fn new(row: u32, shelf: u32, zone: u32) -> Position {
+        Position { row, shelf, zone }
+    }
+
+    pub fn as_tuple(&self) -> (u32, u32, u32) {
+        (self.row, self.shelf, self.zone)
+    }
+
+    // number of steps between two positions, one step per row, shelf or zone
+    pub fn distance(&self, other: &Position) -> u32 {
+        self.row.abs_diff(other.row)
+            + self.shelf.abs_diff(other.shelf)
+            + self.zone.abs_diff(other.zone)
+    }
+}
+
+impl From<(u32, u32, u32)> for Position {
+    fn from(pos: (u32, u32, u32)) -> Position {
+        let (row, shelf, zone) = pos;
+        Position { row, shelf, zone }
+    }
+}
+
+impl Display for Position {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let (row, shelf, zone) = self.as_tuple();
+        write!(f, "({}, {}, {})", row, shelf, zone)
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub enum Quality {
+    Fragile { expiration_date: [u32; 3], row: u32 },
+    Oversized { continuous_zones: u32 },
+    Normal,
+    Refrigerated { min_temp: i32, max_temp: i32 }, // °C the item can be kept at
+    Hazardous { class: u32 },                     // see SegregationRules
+}
+
+impl Quality {
+    // how many neighbouring zones of one shelf the item covers
+    pub fn span(&self) -> u32 {
+        match self {
+            Quality::Oversized { continuous_zones } => *continuous_zones,
+            _ => 1,
+        }
+    }
+}
+
+impl Display for Quality {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Quality::Fragile {
+                expiration_date,
+                row,
+            } => {
+                write!(
+                    f,
+                    "Fragile (Expiration: {:?}, Row: {})",
+                    expiration_date, row
+                )
+            }
+            Quality::Oversized { continuous_zones } => {
+                write!(f, "Oversized (Continuous Zones: {})", continuous_zones)
+            }
+            Quality::Normal => {
+                write!(f, "Normal")
+            }
+            Quality::Refrigerated { min_temp, max_temp } => {
+                write!(f, "Refrigerated ({}..{} °C)", min_temp, max_temp)
+            }
+            Quality::Hazardous { class } => {
+                write!(f, "Hazardous (Class: {})", class)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, Hash, PartialEq, Serialize, Deserialize)]
+pub struct Item {
+    pub id: u32,
+    pub name: String,
+    pub quantity: u32,
+    pub quality: Quality,
+}
+
+// An item that is still to be stored: without an id the placement picks one
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize)]
+pub struct NewItem {
+    pub id: Option<u32>,
+    pub name: String,
+    pub quantity: u32,
+    pub quality: Quality,
+}
+
+impl Display for Item {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} - {}, quantity: {}, quality: {}",
+            self.id, self.name, self.quantity, self.quality
+        )
+    }
+}
+
+// every item covers at least one zone, an Oversized item of 0 zones would be stored nowhere
+pub(crate) fn check_span(item: &Item) -> Result<(), MyError> {
+    match item.quality.span() {
+        0 => Err(MyError::InvalidItem(
+            item.clone(),
+            "an oversized item must span at least one zone".to_string(),
+        )),
+        _ => Ok(()),
+    }
+}
+
+#[derive(Debug)]
+pub struct Placement {
+    pub(crate) layout: Layout,
+    pub(crate) storage: Storage, // which item covers each cell, and the items by id
+    pub(crate) allocation_strategy: Box<dyn Strategy>,
+    pub(crate) name_map: HashMap<String, Item>, // given an item NAME, return me the ITEM
+    pub(crate) position_map: HashMap<u32, Vec<Position>>, // given an item ID, return me all positions
+    pub(crate) filter_list: Vec<Box<dyn Filter>>,
+    pub(crate) orders: OrderBook,
+    pub(crate) intake: HashMap<u32, u64>, // given an item ID, when it came in (higher = later)
+    pub(crate) next_intake: u64,
+    pub(crate) catalog: Catalog,
+    pub(crate) next_id: u32, // generated ids start here, always above every id seen so far
+    pub(crate) inbound: BTreeMap<String, Reservation>, // ASN -> cells held for that delivery
+    pub(crate) observers: Vec<Box<dyn Observer>>,
+    pub(crate) expired_notified: HashSet<u32>, // ids the observers already heard expired
+    pub(crate) reorder_rules: BTreeMap<String, ReorderRule>, // by item name
+}
+impl Default for Placement {
+    fn default() -> Placement {
+        Placement::new()
+    }
+}
+
+impl Placement {
+    pub fn new() -> Placement {
+        Placement::with_layout(Layout::default())
+    }
+
+    pub fn with_layout(layout: Layout) -> Placement {
+        let storage = Storage::new(layout.rows, layout.shelves, layout.zones);
+        let name_map = HashMap::new();
+        let position_map = HashMap::new();
+
+        Placement {
+            layout,
+            storage,
+            allocation_strategy: Box::from(RoundRobin {}),
+            name_map,
+            position_map,
+            filter_list: Vec::new(),
+            orders: OrderBook::default(),
+            intake: HashMap::new(),
+            next_intake: 0,
+            catalog: Catalog::default(),
+            next_id: 1,
+            inbound: BTreeMap::new(),
+            observers: Vec::new(),
+            expired_notified: HashSet::new(),
+            reorder_rules: BTreeMap::new(),
+        }
+    }
+
+    pub fn layout(&self) -> &Layout {
+        &self.layout
+    }
+
+    // read-only view of the cells and the item table
+    pub fn storage(&self) -> &Storage {
+        &self.storage
+    }
+
+    pub fn configure_filters(&mut self, list: Vec<Box<dyn Filter>>) {
+        self.filter_list = list
+    }
+
+    pub fn configure_strategy(&mut self, strategy: Box<dyn Strategy>) {
+        self.allocation_strategy = strategy
+    }
+
+    // observers are told about events in the order they were added
+    pub fn add_observer(&mut self, observer: Box<dyn Observer>) {
+        self.observers.push(observer)
+    }
+
+    // hazardous classes a and b must be stored at least `distance` positions apart, 0 lifts the rule
+    pub fn set_segregation(&mut self, a: u32, b: u32, distance: u32) {
+        self.layout.segregation.set(a, b, distance)
+    }
+
+    // tags a whole row (shelf = None) or one shelf with a temperature zone, None clears the tag
+    pub fn set_temperature_zone(
+        &mut self,
+        row: u32,
+        shelf: Option<u32>,
+        zone: Option<TemperatureZone>,
+    ) {
+        match (shelf, zone) {
+            (None, Some(zone)) => self.layout.row_temperatures.insert(row, zone),
+            (None, None) => self.layout.row_temperatures.remove(&row),
+            (Some(shelf), Some(zone)) => self.layout.shelf_temperatures.insert((row, shelf), zone),
+            (Some(shelf), None) => self.layout.shelf_temperatures.remove(&(row, shelf)),
+        };
+    }
+
+    // first filter that refuses the item wins
+    fn check_filters(&self, item: &Item) -> Result<(), Rejection> {
+        self.filter_list
+            .iter()
+            .try_for_each(|filt| filt.check_allowed(item, &self.storage))
+    }
+
+    // An item that a delivery was announced for goes into the cells held for it, as long as
+    // it still suits them; anything else is placed by the strategy.
+    pub fn add_item(&mut self, item: Item) -> Result<(), MyError> {
+        let asn = self.matching_reservation(&item);
+        self.place(item, asn)
+    }
+
+    // add_item, with the cells reserved for delivery `asn` instead of the strategy when given
+    pub(crate) fn place(&mut self, item: Item, asn: Option<String>) -> Result<(), MyError> {
+        if self.storage.items.contains_key(&item.id) {
+            return Err(MyError::DuplicateId(item.id));
+        }
+        check_span(&item)?;
+        if let Err(rejection) = self.check_filters(&item) {
+            for observer in &self.observers {
+                observer.blocked_by_filter(&item, &rejection);
+            }
+            return Err(MyError::BlockedByFilter(item, Box::new(rejection)));
+        }
+
+        let position = match asn {
+            Some(asn) => self.claim_reservation(&asn, &item),
+            None => match self
+                .allocation_strategy
+                .allocate(&item, &self.storage, &self.layout)
+            {
+                // whatever the strategy does, hazardous goods never end up too close together
+                Some(position) => self
+                    .check_segregation(&item, &position)
+                    .map(|_| position),
+                None => Err(MyError::FailedAllocation(item.clone())),
+            },
+        }
+        .and_then(|position| match self.storage.is_free(&position) {
+            true => Ok(position),
+            false => Err(MyError::FailedAdd(item.clone())),
+        });
+        let position = match position {
+            Ok(position) => position,
+            Err(err) => {
+                for observer in &self.observers {
+                    observer.allocation_failed(&item);
+                }
+                return Err(err);
+            }
+        };
+
+        self.name_map.insert(item.name.clone(), item.clone());
+        self.occupy(&item, position);
+        self.intake.insert(item.id, self.next_intake);
+        self.next_intake += 1;
+        // external ids are accepted as they are, generated ones simply continue after them
+        if let Some(next) = item.id.checked_add(1) {
+            self.next_id = self.next_id.max(next);
+        }
+        for observer in &self.observers {
+            observer.item_added(&item, &self.position_map[&item.id]);
+        }
+        self.storage.items.insert(item.id, item);
+        Ok(())
+    }
+
+    // Stores the item under its own id or, without one, under next_free_id.
+    // Returns the id it was stored under.
+    pub fn add_new_item(&mut self, new: NewItem) -> Result<u32, MyError> {
+        let item = self.with_id(new);
+        let id = item.id;
+        self.add_item(item)?;
+        Ok(id)
+    }
+
+    // the item as it will be stored, with next_free_id if it came without an id
+    pub(crate) fn with_id(&self, new: NewItem) -> Item {
+        Item {
+            id: new.id.unwrap_or(self.next_free_id()),
+            name: new.name,
+            quantity: new.quantity,
+            quality: new.quality,
+        }
+    }
+
+    // id to give an item that comes in without one: above every id seen so far, or the
+    // lowest unused one once an imported u32::MAX leaves nothing above
+    pub fn next_free_id(&self) -> u32 {
+        if !self.storage.items.contains_key(&self.next_id) {
+            return self.next_id;
+        }
+        (1..=u32::MAX)
+            .find(|id| !self.storage.items.contains_key(id))
+            .expect("there are fewer items than ids")
+    }
+
+    fn check_segregation(&self, item: &Item, position: &Position) -> Result<(), MyError> {
+        if let Quality::Hazardous { class } = item.quality {
+            let hazards: Vec<(Position, u32, u32)> = self
+                .storage
+                .hazards()
+                .into_iter()
+                .filter(|(_, id, _)| *id != item.id)
+                .collect();
+            if let Some((other, required, actual)) =
+                self.layout.segregation.violation(class, position, &hazards)
+            {
+                return Err(MyError::SegregationViolation {
+                    id: item.id,
+                    other,
+                    required,
+                    actual,
+                });
+            }
+            // cells held for hazardous goods count as if the goods were already there
+            let reserved = self.storage.reserved_hazards();
+            if let Some((asn, required, actual)) =
+                self.layout.segregation.violation(class, position, &reserved)
+            {
+                return Err(MyError::InvalidPosition(
+                    *position,
+                    format!(
+                        "must be at least {} positions away from the cells held for delivery {} (would be {})",
+                        required, asn, actual
+                    ),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    // marks the cells the item covers from `position` on as occupied
+    fn occupy(&mut self, item: &Item, position: Position) {
+        let span = item.quality.span();
+        let positions: Vec<Position> = (position.zone..position.zone + span)
+            .map(|zone| Position::new(position.row, position.shelf, zone))
+            .collect();
+        for pos in &positions {
+            self.storage.set_owner(pos, Some(item.id));
+        }
+        self.position_map.insert(item.id, positions);
+    }
+
+    // frees every cell of the item, the item itself stays in the item table and name_map
+    fn vacate(&mut self, id: u32) {
+        let existing_positions = self.position_map.remove(&id);
+        for pos in existing_positions.into_iter().flatten() {
+            self.storage.set_owner(&pos, None);
+        }
+    }
+
+    // Moves a stored item so that it starts at `to`, checking the same rules as allocation:
+    // free cells, fragile row limit, temperature zone and hazardous segregation.
+    pub fn move_item(&mut self, id: u32, to: Position) -> Result<Vec<Position>, MyError> {
+        let item = self.storage.items.get(&id).cloned().ok_or(MyError::UnknownId(id))?;
+        let from = self
+            .position_map
+            .get(&id)
+            .and_then(|positions| positions.first().copied())
+            .ok_or(MyError::UnknownId(id))?;
+
+        self.vacate(id);
+        let result = self
+            .check_fit(&item, &to)
+            .and_then(|_| self.check_segregation(&item, &to));
+        match result {
+            Ok(()) => {
+                self.occupy(&item, to);
+                Ok(self.position_map.get(&id).cloned().unwrap_or_default())
+            }
+            Err(err) => {
+                self.occupy(&item, from);
+                Err(err)
+            }
+        }
+    }
+
+    // can the item start at `pos`, ignoring hazardous segregation
+    pub fn check_fit(&self, item: &Item, pos: &Position) -> Result<(), MyError> {
+        check_span(item)?;
+        if !self.storage.contains(pos) {
+            return Err(MyError::InvalidPosition(*pos, "outside the layout".to_string()));
+        }
+        let span = item.quality.span();
+        let Some(end) = pos.zone.checked_add(span) else {
+            return Err(MyError::InvalidPosition(*pos, "the span runs past the layout".to_string()));
+        };
+        for zone in pos.zone..end {
+            let cell = Position::new(pos.row, pos.shelf, zone);
+            if !self.storage.contains(&cell) {
+                return Err(MyError::InvalidPosition(cell, "outside the layout".to_string()));
+            }
+            if self.storage.is_occupied(&cell) {
+                return Err(MyError::InvalidPosition(cell, "occupied".to_string()));
+            }
+            if let Some(asn) = self.storage.reservation(&cell) {
+                return Err(MyError::InvalidPosition(cell, format!("reserved for delivery {}", asn)));
+            }
+        }
+        match item.quality {
+            Quality::Fragile { row, .. } if pos.row >= row => Err(MyError::InvalidPosition(
+                *pos,
+                format!("fragile item must stay below row {}", row),
+            )),
+            Quality::Refrigerated { min_temp, max_temp } => {
+                match self.temperature_problem(min_temp, max_temp, pos) {
+                    Some(reason) => Err(MyError::InvalidPosition(*pos, reason)),
+                    None => Ok(()),
+                }
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // why an item kept at min_temp..max_temp °C cannot be at `pos`, None if it can
+    pub(crate) fn temperature_problem(
+        &self,
+        min_temp: i32,
+        max_temp: i32,
+        pos: &Position,
+    ) -> Option<String> {
+        match self.layout.temperature_at(pos) {
+            Some(zone) if min_temp <= zone.min && zone.max <= max_temp => None,
+            Some(zone) => Some(format!(
+                "zone is {}, item needs {}..{} °C",
+                zone, min_temp, max_temp
+            )),
+            None => Some("not temperature controlled".to_string()),
+        }
+    }
+
+    pub fn remove_item(&mut self, id: u32) -> Result<(), MyError> {
+        // check if ID exists, else errors out
+        let name_ref = match self.storage.items.get(&id) {
+            Some(item) => item,
+            None => return Err(MyError::FailedRemove(id)),
+        };
+        // open orders still count on it, confirm or cancel them first
+        if self.available_quantity(id) < name_ref.quantity {
+            return Err(MyError::ItemReserved(id));
+        }
+
+        let name_ref = name_ref.name.clone();
+        self.vacate(id);
+        if let Some(item) = self.storage.items.remove(&id) {
+            for observer in &self.observers {
+                observer.item_removed(&item);
+            }
+        }
+        self.intake.remove(&id);
+        self.expired_notified.remove(&id);
+        self.catalog.forget_lot(id);
+
+        // other lots may share the name, keep name search pointing at one of them
+        match self.storage.items.values().find(|item| item.name == name_ref) {
+            Some(other) => self.name_map.insert(name_ref, other.clone()),
+            None => self.name_map.remove(&name_ref),
+        };
+        Ok(())
+    }
+
+    // keeps every copy of the item (item table, name_map) in sync
+    pub(crate) fn set_quantity(&mut self, id: u32, quantity: u32) {
+        if let Some(item) = self.storage.items.get_mut(&id) {
+            item.quantity = quantity;
+            if let Some(named) = self.name_map.get_mut(&item.name)
+                && named.id == id
+            {
+                named.quantity = quantity;
+            }
+        }
+    }
+
+    pub fn alphabetical(&self) -> Vec<Item> {
+        self.storage
+            .items
+            .values()
+            .sorted_by(|a, b| Ord::cmp(&a.name.to_lowercase(), &b.name.to_lowercase()))
+            .cloned()
+            .collect()
+    }
+
+    pub fn id_search(&mut self, search_id: u32) -> Option<&Item> {
+        self.storage.items.get(&search_id)
+    }
+    pub fn name_search(&mut self, search_name: String) -> Option<&Item> {
+        self.name_map.get(&search_name)
+    }
+
+    pub fn check_expired_products(&self, expiration_date: [u32; 3]) -> Option<HashSet<Item>> {
+        let [current_day, current_month, current_year] = expiration_date;
+        let mut expired_items = HashSet::new();
+
+        for item in self.storage.items.values() {
+            if let Quality::Fragile {
+                expiration_date: item_expiration_date,
+                ..
+            } = &item.quality
+            {
+                let item_day = item_expiration_date[0];
+                let item_month = item_expiration_date[1];
+                let item_year = item_expiration_date[2];
+
+                if current_year > item_year
+                    || (current_year == item_year && current_month > item_month)
+                    || (current_year == item_year
+                        && current_month == item_month
+                        && current_day >= item_day)
+                {
+                    expired_items.insert(item.clone());
+                }
+            }
+        }
+
+        if expired_items.is_empty() {
+            None
+        } else {
+            Some(expired_items)
+        }
+    }
+
+    // Tells the observers about every item expired by `date` they have not heard of yet and
+    // returns those items, by id. check_expired_products only looks.
+    pub fn notify_expired(&mut self, date: [u32; 3]) -> Vec<Item> {
+        let newly_expired: Vec<Item> = self
+            .check_expired_products(date)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|item| !self.expired_notified.contains(&item.id))
+            .sorted_by_key(|item| item.id)
+            .collect();
+        for item in &newly_expired {
+            for observer in &self.observers {
+                observer.item_expired(item, date);
+            }
+            self.expired_notified.insert(item.id);
+        }
+        newly_expired
+    }
+
+    pub fn position_search(&mut self, id: u32) -> Option<Vec<Position>> {
+        self.position_map.get(&id).cloned()
+    }
+}
+
+impl Display for Placement {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        // sorted by position, Oversized items show the whole span they cover
+        let sorted = self
+            .position_map
+            .iter()
+            .filter_map(|(id, positions)| Some((positions.first()?, positions.last()?, id)))
+            .sorted_by_key(|(first, _, _)| first.as_tuple());
+        for (first, last, id) in sorted {
+            if let Some(item) = self.storage.items.get(id) {
+                if first == last {
+                    writeln!(f, "{} -> {}", first, item)?;
+                } else {
+                    writeln!(f, "{}..{} -> {}", first, last, item)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct RoundRobin {}
+
+impl RoundRobin {
+    fn is_position_valid(
+        &self,
+        pos: &Position,
+        item: &Item,
+        storage: &Storage,
+        layout: &Layout,
+        hazards: &[(Position, u32, u32)],
+        reserved: &[(Position, String, u32)],
+    ) -> bool {
+        match &item.quality {
+            Quality::Fragile { row, .. } => pos.row < *row,
+            // the whole span must be free and inside the layout
+            Quality::Oversized { continuous_zones } => storage.is_run_free(pos, *continuous_zones),
+            Quality::Normal => true,
+            // the zone must stay within what the item tolerates
+            Quality::Refrigerated { min_temp, max_temp } => layout
+                .temperature_at(pos)
+                .is_some_and(|zone| *min_temp <= zone.min && zone.max <= *max_temp),
+            Quality::Hazardous { class } => {
+                layout.segregation.violation(*class, pos, hazards).is_none()
+                    && layout.segregation.violation(*class, pos, reserved).is_none()
+            }
+        }
+    }
+}
+
+impl Strategy for RoundRobin {
+    fn allocate(
+        &mut self,
+        item: &Item,
+        storage: &Storage,
+        layout: &Layout,
+    ) -> Option<Position> {
+        match item.quality {
+            // the first position that fits a span is always the start of a free run
+            Quality::Oversized { continuous_zones } => {
+                return storage.first_free_run(continuous_zones);
+            }
+            Quality::Normal => return storage.first_free_run(1),
+            // free cells come in layout order, row first, so if the first one is too high
+            // every other one is as well
+            Quality::Fragile { row, .. } => {
+                return storage.first_free_run(1).filter(|pos| pos.row < row);
+            }
+            _ => {}
+        }
+        // stored hazards and the cells held for hazardous deliveries
+        let (hazards, reserved) = match item.quality {
+            Quality::Hazardous { .. } => (storage.hazards(), storage.reserved_hazards()),
+            _ => (Vec::new(), Vec::new()),
+        };
+        layout.positions().find(|pos| {
+            storage.is_free(pos)
+                && self.is_position_valid(pos, item, storage, layout, &hazards, &reserved)
+        })
+    }
+}
+
+// Normal and Oversized items go into the shortest free run they fit in, which keeps long runs
+// intact for large items. Everything else is placed like RoundRobin does.
+#[derive(Debug)]
+pub struct BestFit {}
+
+impl Strategy for BestFit {
+    fn allocate(
+        &mut self,
+        item: &Item,
+        storage: &Storage,
+        layout: &Layout,
+    ) -> Option<Position> {
+        match item.quality {
+            Quality::Normal => storage.best_free_run(1),
+            Quality::Oversized { continuous_zones } => storage.best_free_run(continuous_zones),
+            _ => RoundRobin {}.allocate(item, storage, layout),
+        }
+    }
+}
+
+// Two types of filter
+// a) Avoid Oversize with too big size
+// b) Avoid Fragile with too small max.row
+#[derive(Debug)]
+pub struct AvoidTooLarge {
+    pub cutoff: u32,
+}
+
+impl Filter for AvoidTooLarge {
+    fn check_allowed(
+        &self,
+        item: &Item,
+        _storage: &Storage,
+    ) -> Result<(), Rejection> {
+        match &item.quality {
+            Quality::Fragile { .. }
+            | Quality::Normal
+            | Quality::Refrigerated { .. }
+            | Quality::Hazardous { .. } => Ok(()),
+            Quality::Oversized { continuous_zones } if continuous_zones <= &self.cutoff => Ok(()),
+            Quality::Oversized { continuous_zones } => Err(Rejection {
+                filter: "AvoidTooLarge".to_string(),
+                rule: format!("oversized items may span at most {} zones", self.cutoff),
+                value: format!("{} zones", continuous_zones),
+            }),
+        }
+    }
+}
+#[derive(Debug)]
+pub struct AvoidTooFragile {
+    pub cutoff: u32,
+}
+
+impl Filter for AvoidTooFragile {
+    fn check_allowed(
+        &self,
+        item: &Item,
+        _storage: &Storage,
+    ) -> Result<(), Rejection> {
+        match &item.quality {
+            Quality::Oversized { .. }
+            | Quality::Normal
+            | Quality::Refrigerated { .. }
+            | Quality::Hazardous { .. } => Ok(()),
+            Quality::Fragile { row, .. } if row >= &self.cutoff => Ok(()),
+            Quality::Fragile { row, .. } => Err(Rejection {
+                filter: "AvoidTooFragile".to_string(),
+                rule: format!("fragile items must allow at least row {}", self.cutoff),
+                value: format!("max row {}", row),
+            }),
+        }
+    }
+}
@@ -0,0 +1,179 @@
+use crate::server::{item_json, stats_json};
+use crate::{MyError, NewItem, Placement, Quality};
+use itertools::Itertools;
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::io::{BufRead, Write};
+
+// One line of input, e.g. {"command": "remove_item", "id": 3}
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum Command {
+    AddItem {
+        id: Option<u32>, // generated when left out
+        name: String,
+        quantity: u32,
+        quality: Quality,
+    },
+    RemoveItem {
+        id: u32,
+    },
+    IdSearch {
+        id: u32,
+    },
+    NameSearch {
+        name: String,
+    },
+    PositionSearch {
+        id: u32,
+    },
+    CheckExpiredProducts {
+        date: [u32; 3], // [day, month, year]
+    },
+    Alphabetical,
+    Stats,
+    Validate,
+}
+
+// Runs one command, the result is what goes under "result" in the answer
+pub fn execute(placement: &mut Placement, command: Command) -> Result<Value, MyError> {
+    match command {
+        Command::AddItem {
+            id,
+            name,
+            quantity,
+            quality,
+        } => {
+            let id = placement.add_new_item(NewItem {
+                id,
+                name,
+                quantity,
+                quality,
+            })?;
+            Ok(item_json(placement, &placement.storage.items[&id]))
+        }
+        Command::RemoveItem { id } => {
+            placement.remove_item(id)?;
+            Ok(json!({ "removed": id }))
+        }
+        Command::IdSearch { id } => {
+            let item = placement.storage.items.get(&id).ok_or(MyError::UnknownId(id))?;
+            Ok(item_json(placement, item))
+        }
+        Command::NameSearch { name } => {
+            let item = placement
+                .name_map
+                .get(&name)
+                .ok_or(MyError::UnknownName(name))?;
+            Ok(item_json(placement, item))
+        }
+        Command::PositionSearch { id } => {
+            let positions = placement.position_search(id).ok_or(MyError::UnknownId(id))?;
+            Ok(json!(positions))
+        }
+        Command::CheckExpiredProducts { date } => {
+            let expired = placement.check_expired_products(date).unwrap_or_default();
+            Ok(Value::Array(
+                expired
+                    .iter()
+                    .sorted_by_key(|item| item.id)
+                    .map(|item| item_json(placement, item))
+                    .collect(),
+            ))
+        }
+        Command::Alphabetical => Ok(Value::Array(
+            placement
+                .alphabetical()
+                .iter()
+                .map(|item| item_json(placement, item))
+                .collect(),
+        )),
+        Command::Stats => Ok(stats_json(placement)),
+        Command::Validate => Ok(Value::Array(
+            placement
+                .validate()
+                .iter()
+                .map(|violation| json!(violation.to_string()))
+                .collect(),
+        )),
+    }
+}
+
+// the answer to one input line
+pub fn answer(placement: &mut Placement, line: &str) -> Value {
+    let result = serde_json::from_str::<Command>(line)
+        .map_err(|err| MyError::InvalidRequest(err.to_string()))
+        .and_then(|command| execute(placement, command));
+    match result {
+        Ok(result) => json!({ "ok": true, "result": result }),
+        Err(err) => json!({
+            "ok": false,
+            "error": { "kind": err.kind(), "message": err.to_string() },
+        }),
+    }
+}
+
+// Reads one JSON command per line until the input ends and writes one JSON answer per line.
+// Blank lines are skipped.
+pub fn run(placement: &mut Placement, input: impl BufRead, mut output: impl Write) -> Result<(), MyError> {
+    for line in input.lines() {
+        let line = line.map_err(MyError::IOError)?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let answer = answer(placement, &line);
+        writeln!(output, "{}", answer).map_err(MyError::IOError)?;
+        output.flush().map_err(MyError::IOError)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Layout;
+
+    #[test]
+    fn a_good_line_is_answered_with_its_result() {
+        let mut placement = Placement::with_layout(Layout::new(1, 1, 5));
+        let reply = answer(
+            &mut placement,
+            r#"{"command": "add_item", "name": "Milk", "quantity": 2, "quality": "Normal"}"#,
+        );
+        assert_eq!(reply["ok"], json!(true));
+        assert_eq!(reply["result"]["id"], json!(1));
+        assert_eq!(reply["result"]["positions"], json!([{ "row": 0, "shelf": 0, "zone": 0 }]));
+
+        let reply = answer(&mut placement, r#"{"command": "position_search", "id": 1}"#);
+        assert_eq!(reply["result"], json!([{ "row": 0, "shelf": 0, "zone": 0 }]));
+    }
+
+    #[test]
+    fn a_bad_line_is_answered_with_an_error() {
+        let mut placement = Placement::with_layout(Layout::new(1, 1, 5));
+        let reply = answer(&mut placement, r#"{"command": "fly_away"}"#);
+        assert_eq!(reply["ok"], json!(false));
+        assert_eq!(reply["error"]["kind"], json!("InvalidRequest"));
+
+        let reply = answer(&mut placement, r#"{"command": "remove_item", "id": 7}"#);
+        assert_eq!(reply["ok"], json!(false));
+        assert_eq!(reply["error"]["kind"], json!("FailedRemove"));
+    }
+
+    #[test]
+    fn run_answers_every_line_and_skips_blank_ones() {
+        let mut placement = Placement::with_layout(Layout::new(1, 1, 5));
+        let input = "{\"command\": \"stats\"}\n\nnot json\n{\"command\": \"validate\"}\n";
+        let mut output = Vec::new();
+        run(&mut placement, input.as_bytes(), &mut output).unwrap();
+
+        let answers: Vec<Value> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        let ok: Vec<&Value> = answers.iter().map(|answer| &answer["ok"]).collect();
+        assert_eq!(ok, vec![&json!(true), &json!(false), &json!(true)]);
+        assert_eq!(answers[2]["result"], json!([]));
+    }
+}
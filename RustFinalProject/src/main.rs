@@ -1,467 +1,23 @@
-use itertools::{Itertools, iproduct};
-use std::collections::{BTreeMap, HashMap, HashSet};
-use std::fmt::{Debug, DebugSet, Display, Formatter, write};
-use std::hash::{Hash, Hasher};
-use std::num::ParseIntError;
-use thiserror::Error;
-
-const MAXPOSITION: u32 = 10;
-
-#[derive(Error, Debug)]
-enum MyError {
-    // failed to add
-    #[error("Could not add item {0}")]
-    FailedAdd(Item),
-    // failed to remove
-    #[error("Could not remove item with Id {0}")]
-    FailedRemove(u32),
-    // got filtered
-    #[error("The item {0} triggered some filter")]
-    BlockedByFilter(Item),
-    // failed to find an alloc
-    #[error("The allocator could not find a position for item {0}")]
-    FailedAllocation(Item),
-    // IO error
-    #[error("IO error: {0}")]
-    IOError(std::io::Error),
-    // Parse error
-    #[error("Parse int error: {0}")]
-    ParseIntError(ParseIntError),
-    //Invalid Error Format
-    #[error("Invalid Date Format: {0}")]
-    InvalidDateFormat(String),
-    //Wrong Option
-    #[error("Wrong Option: {0}")]
-    WrongOption(String),
-}
-
-trait Filter: Debug {
-    fn check_allowed(&self, item: &Item, map: &HashMap<Position, Option<Item>>) -> bool;
-}
-
-trait Strategy: Debug {
-    fn allocate(&mut self, item: &Item, map: &HashMap<Position, Option<Item>>) -> Option<Position>;
-}
-
-#[derive(Copy, Clone, Debug)]
-struct Position {
-    row: u32,
-    shelf: u32,
-    zone: u32,
-    occupied: bool,
-}
-
-impl Position {
-    fn new(row: u32, shelf: u32, zone: u32) -> Position {
-        Position {
-            row,
-            shelf,
-            zone,
-            occupied: false,
-        }
-    }
-
-    fn as_tuple(&self) -> (u32, u32, u32) {
-        (self.row, self.shelf, self.zone)
-    }
-}
-
-impl PartialEq for Position {
-    fn eq(&self, other: &Position) -> bool {
-        self.row == other.row && self.shelf == other.shelf && self.zone == other.zone
-        // doesnt take self.occupied into account!
-    }
-}
-
-impl Eq for Position {}
-
-impl Hash for Position {
-    fn hash<H: Hasher>(&self, state: &mut H) {
-        self.row.hash(state);
-        self.shelf.hash(state);
-        self.zone.hash(state);
-        // doesnt take self.occupied into account!
-    }
-}
-
-impl From<(u32, u32, u32)> for Position {
-    fn from(pos: (u32, u32, u32)) -> Position {
-        let (row, shelf, zone) = pos;
-        Position {
-            row,
-            shelf,
-            zone,
-            occupied: false,
-        }
-    }
-}
-
-impl Display for Position {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "({}, {}, {})", self.row, self.shelf, self.zone)
-    }
-}
-
-#[derive(Debug, Clone, Eq, PartialEq, Hash)]
-enum Quality {
-    Fragile { expiration_date: [u32; 3], row: u32 },
-    Oversized { continuous_zones: u32 },
-    Normal,
-}
-
-impl Display for Quality {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Quality::Fragile {
-                expiration_date,
-                row,
-            } => {
-                write!(
-                    f,
-                    "Fragile (Expiration: {:?}, Row: {})",
-                    expiration_date, row
-                )
-            }
-            Quality::Oversized { continuous_zones } => {
-                write!(f, "Oversized (Continuous Zones: {})", continuous_zones)
-            }
-            Quality::Normal => {
-                write!(f, "Normal")
-            }
-        }
-    }
-}
-
-#[derive(Debug, Clone, Eq, Hash, PartialEq)]
-struct Item {
-    id: u32,
-    name: String,
-    quantity: u32,
-    quality: Quality,
-}
-
-impl Display for Item {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "{} - {}, quantity: {}, quality: {}",
-            self.id, self.name, self.quantity, self.quality
-        )
-    }
-}
-
-#[derive(Debug)]
-struct Placement {
-    map: HashMap<Position, Option<Item>>,
-    allocation_strategy: Box<dyn Strategy>,
-    id_map: HashMap<u32, Item>, // given an item ID, return me the ITEM
-    name_map: HashMap<String, Item>, // given an item NAME, return me the ITEM
-    position_map: HashMap<u32, Vec<Position>>, // given an item ID, return me all positions
-    filter_list: Vec<Box<dyn Filter>>,
-}
-impl Placement {
-    fn new() -> Placement {
-        // pre-generate all positions
-        let mut map = HashMap::new();
-        let mut id_map = HashMap::new();
-        let mut name_map = HashMap::new();
-        let mut position_map = HashMap::new();
-
-        for (i, j, k) in iproduct!(0..MAXPOSITION, 0..MAXPOSITION, 0..MAXPOSITION) {
-            map.insert(Position::from((i, j, k)), None);
-        }
-
-        Placement {
-            map,
-            allocation_strategy: Box::from(RoundRobin {}),
-            id_map,
-            name_map,
-            position_map,
-            filter_list: Vec::new(),
-        }
-    }
-
-    fn configure_filters(&mut self, list: Vec<Box<dyn Filter>>) {
-        self.filter_list = list
-    }
-
-    fn is_allowed_by_filters(&self, item: &Item) -> bool {
-        self.filter_list
-            .iter()
-            .all(|filt| filt.check_allowed(item, &self.map))
-    }
-
-    fn add_item(&mut self, item: Item) -> Result<(), MyError> {
-        if !self.is_allowed_by_filters(&item) {
-            return Err(MyError::BlockedByFilter(item));
-        }
-
-        let mut position = match self.allocation_strategy.allocate(&item, &self.map) {
-            Some(position) => position,
-            None => return Err(MyError::FailedAllocation(item)),
-        };
-        position.occupied = true;
-
-        self.id_map.insert(item.id.clone(), item.clone());
-        self.name_map.insert(item.name.clone(), item.clone());
-
-        match &item.quality {
-            Quality::Normal | Quality::Fragile { .. } => {
-                self.map.remove(&position); // remove old key with OCCUPIED = false
-                self.map.insert(position, Some(item.clone())); // add with OCCUPIED = true
-                self.position_map.insert(item.id, vec![position]);
-            }
-            Quality::Oversized { continuous_zones } => {
-                for k in position.zone..(position.zone + continuous_zones) {
-                    let mut temp = Position::new(position.row, position.shelf, k);
-                    temp.occupied = true;
-                    if k == position.zone {
-                        self.map.remove(&temp); // remove old key with OCCUPIED = false
-                        self.map.insert(temp, Some(item.clone())); // add with OCCUPIED = true
-                        self.position_map.insert(item.id, vec![position]);
-                    } else {
-                        self.map.remove(&temp); // remove old key with OCCUPIED = false
-                        self.map.insert(temp, None); // add with OCCUPIED = true
-                        // BUT! No item, since it is occupied by a Oversized item in an earlier position
-
-                        if let Some(positions) = self.position_map.get_mut(&item.id) {
-                            positions.push(temp);
-                        }
-                    }
-                }
-            }
-        }
-
-        let test = self.map.get(&position);
-        if test.is_none() {
-            return Err(MyError::FailedAdd(item.clone()))
-        }
-        Ok(())
-    }
-
-    fn remove_item(&mut self, id: u32) -> Result<(), MyError> {
-        /*
-           if let Some((position, item)) = self.map {
-               match &item.quality {
-                   Quality::Normal | Quality::Fragile { .. } => {
-                       let mut temp = position;
-                       temp.occupied = false;
-
-                       self.map.insert(temp, None); // remove old Key with OCCUPIED = false
-                   }
-                   Quality::Oversized { continuous_zones } => {
-                       for k in position.zone..(position.zone + continuous_zones) {
-                           let mut temp = Position::new(position.row, position.shelf, k);
-                           temp.occupied = false;
-
-                           if k == position.zone {
-                               self.map.insert(temp, None); // remove old Key with OCCUPIED = false
-                           } else {
-                               self.map.insert(temp, None);
-                           }
-                       }
-                   }
-               }
-           }
-
-        */
-        // check if ID exists, else errors out
-        let name_ref = match self.id_map.get(&id) {
-            Some(item) => item,
-            None => return Err(MyError::FailedRemove(id)),
-        };
-
-        let existing_positions = self.position_map.get(&id);
-        for pos in existing_positions.into_iter().flatten() {
-            let mut tmp = Position::from((pos.row, pos.shelf, pos.zone));
-            tmp.occupied = false;
-            self.map.remove(&tmp); // remove old KEY with OCCUPIED = true
-            self.map.insert(tmp, None); // add new KEY with OCCUPIED = false
-        }
-        let name_ref = &name_ref.name;
-        self.name_map.remove(name_ref);
-        self.id_map.remove(&id);
-        self.position_map.remove(&id);
-        Ok(())
-    }
-
-    fn alphabetical(&self) -> Vec<Item> {
-        let list = self
-            .map
-            .values()
-            .filter_map(|v| v.clone())
-            .collect::<Vec<Item>>();
-        let sorted_list = list
-            .iter()
-            .sorted_by(|a, b| Ord::cmp(&a.name.to_lowercase(), &b.name.to_lowercase()))
-            .cloned()
-            .collect();
-        sorted_list
-    }
-
-    fn id_search(&mut self, search_id: u32) -> Option<&Item> {
-        self.id_map.get(&search_id)
-    }
-    fn name_search(&mut self, search_name: String) -> Option<&Item> {
-        self.name_map.get(&search_name)
-    }
-
-    fn check_expired_products(&self, expiration_date: [u32; 3]) -> Option<HashSet<Item>> {
-        let [current_day, current_month, current_year] = expiration_date;
-        let mut expired_items = HashSet::new();
-
-        for (_, opt_item) in &self.map {
-            if let Some(item) = opt_item {
-                if let Quality::Fragile {
-                    expiration_date: item_expiration_date,
-                    ..
-                } = &item.quality
-                {
-                    let item_day = item_expiration_date[0];
-                    let item_month = item_expiration_date[1];
-                    let item_year = item_expiration_date[2];
-
-                    if current_year > item_year
-                        || (current_year == item_year && current_month > item_month)
-                        || (current_year == item_year
-                            && current_month == item_month
-                            && current_day >= item_day)
-                    {
-                        expired_items.insert(item.clone());
-                    }
-                }
-            }
-        }
-
-        if expired_items.is_empty() {
-            None
-        } else {
-            Some(expired_items)
-        }
-    }
-
-    fn position_search(&mut self, id: u32) -> Option<Vec<Position>> {
-        self.position_map.get(&id).cloned()
-    }
-}
-
-impl Display for Placement {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        for (key, opt) in self.map.iter() {
-            if let Some(item) = opt {
-                write!(f, "{} -> {}\n", key, item)?;
-            }
-        }
-        Ok(())
-    }
-}
-
-#[derive(Debug)]
-struct RoundRobin {}
-
-impl RoundRobin {
-    fn is_position_valid(
-        &self,
-        pos: &Position,
-        item: &Item,
-        map: &HashMap<Position, Option<Item>>,
-    ) -> bool {
-        match &item.quality {
-            Quality::Fragile {
-                expiration_date,
-                row,
-            } => pos.row < *row,
-            Quality::Oversized { continuous_zones } => {
-                if pos.zone + continuous_zones > MAXPOSITION {
-                    // check if there is enough space
-                    false
-                } else {
-                    // then check if existing space is free (not occupied)
-                    let mut flag = true;
-                    for k in pos.zone..(pos.zone + continuous_zones) {
-                        let pos_test = Position::from((pos.row, pos.shelf, k));
-                        let opt = map.get_key_value(&pos_test);
-                        match opt {
-                            Some((k, _)) => {
-                                if k.occupied {
-                                    flag = false;
-                                    break;
-                                }
-                            }
-                            None => {
-                                flag = false;
-                                break;
-                            }
-                        }
-                        return flag;
-                    }
-                    false
-                }
-            }
-            Quality::Normal => true,
-        }
-    }
-}
-
-impl Strategy for RoundRobin {
-    fn allocate(&mut self, item: &Item, map: &HashMap<Position, Option<Item>>) -> Option<Position> {
-        for (i, j, k) in iproduct!(0..MAXPOSITION, 0..MAXPOSITION, 0..MAXPOSITION) {
-            let pos = Position::from((i, j, k));
-            let opt = map.get_key_value(&pos);
-            // this option holds (Position, Option<Item>) that is actually inside the hashmap
-            // now we need to write logic based on Position.occupied
-            match opt {
-                Some((p, _)) => {
-                    if p.occupied {
-                        // println!("Yoo {}{}{} is OCCUPIED!! Not worth our time.", i, j, k);
-                        continue;
-                    } else {
-                        if self.is_position_valid(&pos, item, &map) {
-                            // lets check if satisfies item quality requirements
-                            return Some(p.clone());
-                        } else {
-                            continue;
-                        }
-                    }
-                }
-                None => {
-                    continue;
-                } // should not ever trigger, but it is here anyway
-            }
-        }
-        None
-    }
-}
-
-// Two types of filter
-// a) Avoid Oversize with too big size
-// b) Avoid Fragile with too small max.row
-#[derive(Debug)]
-struct AvoidTooLarge {
-    cutoff: u32,
-}
-
-impl Filter for AvoidTooLarge {
-    fn check_allowed(&self, item: &Item, map: &HashMap<Position, Option<Item>>) -> bool {
-        match &item.quality {
-            Quality::Fragile { .. } | Quality::Normal => true,
-            Quality::Oversized { continuous_zones } => continuous_zones <= &self.cutoff,
-        }
-    }
-}
-#[derive(Debug)]
-struct AvoidTooFragile {
-    cutoff: u32,
-}
-
-impl Filter for AvoidTooFragile {
-    fn check_allowed(&self, item: &Item, map: &HashMap<Position, Option<Item>>) -> bool {
-        match &item.quality {
-            Quality::Oversized { .. } | Quality::Normal => true,
-            Quality::Fragile { row, .. } => row >= &self.cutoff,
-        }
-    }
-}
+mod tui;
+
+use itertools::Itertools;
+use warehouse::catalog::Product;
+use warehouse::events::EventLog;
+use warehouse::export::HeatmapMode;
+use warehouse::grid::GRID_LEGEND;
+use warehouse::machine;
+use warehouse::orders::{ItemRef, Order, OrderLine};
+use warehouse::query::{ItemQuery, QualityKind, SortKey, parse_date};
+use warehouse::replenish::ReorderRule;
+use warehouse::route::{Route, TravelCost};
+use warehouse::server;
+use warehouse::shared::SharedPlacement;
+use warehouse::simulate::{self, SimulationConfig};
+use warehouse::warehouses::Registry;
+use warehouse::{
+    AvoidTooFragile, AvoidTooLarge, BestFit, Filter, Item, Layout, MAXCELLS, MAXPOSITION, MyError,
+    NewItem, Placement, Position, Quality, RoundRobin, Strategy, TemperatureZone,
+};
 
 // Ask for info
 
@@ -513,47 +69,262 @@ fn ask_id() -> Result<u32, MyError> {
     }
 }
 
-fn ask_new_product() -> Result<Item, MyError> {
-    println!("Id:");
-    let mut input_id: String = String::new();
-    let result = std::io::stdin().read_line(&mut input_id);
+// prints the prompt and returns the trimmed answer, empty if the user just hits enter
+fn ask_line(prompt: &str) -> Result<String, MyError> {
+    println!("{}", prompt);
+    let mut input: String = String::new();
+    let result = std::io::stdin().read_line(&mut input);
     if let Err(err) = result {
         return Err(MyError::IOError(err));
     }
-    input_id = input_id.trim().to_string();
+    Ok(input.trim().to_string())
+}
 
-    let result = input_id.parse::<u32>();
-    let input_id = match result {
-        Ok(duration) => duration,
-        Err(err) => return Err(MyError::ParseIntError(err)),
+// "a-b" -> (a, b), "a" -> (a, a), "" -> None
+fn parse_range(input: &str) -> Result<Option<(u32, u32)>, MyError> {
+    if input.is_empty() {
+        return Ok(None);
+    }
+    let (from, to) = input.split_once('-').unwrap_or((input, input));
+    let from = from.trim().parse::<u32>().map_err(MyError::ParseIntError)?;
+    let to = to.trim().parse::<u32>().map_err(MyError::ParseIntError)?;
+    Ok(Some((from, to)))
+}
+
+fn ask_query() -> Result<ItemQuery, MyError> {
+    println!("Leave any field empty to not filter on it");
+    let mut query = ItemQuery::new();
+
+    let qualities = ask_line(
+        "Qualities (any of 0: Fragile, 1: Oversized, 2: Normal, 3: Refrigerated, 4: Hazardous, e.g. 0,2):",
+    )?;
+    for option in qualities.split(',').map(|s| s.trim()).filter(|s| !s.is_empty()) {
+        query = match option {
+            "0" => query.quality(QualityKind::Fragile),
+            "1" => query.quality(QualityKind::Oversized),
+            "2" => query.quality(QualityKind::Normal),
+            "3" => query.quality(QualityKind::Refrigerated),
+            "4" => query.quality(QualityKind::Hazardous),
+            _ => return Err(MyError::WrongOption(option.to_string())),
+        };
+    }
+
+    if let Some((min, max)) = parse_range(&ask_line("Quantity range (min-max):")?)? {
+        query = query.min_quantity(min).max_quantity(max);
+    }
+
+    let from = ask_line("Expiring on or after (xx-xx-xxxx):")?;
+    if !from.is_empty() {
+        query = query.expiring_after(parse_date(&from)?);
+    }
+    let to = ask_line("Expiring on or before (xx-xx-xxxx):")?;
+    if !to.is_empty() {
+        query = query.expiring_before(parse_date(&to)?);
+    }
+
+    if let Some((from, to)) = parse_range(&ask_line("Rows (from-to):")?)? {
+        query = query.rows(from, to);
+    }
+    if let Some((from, to)) = parse_range(&ask_line("Shelves (from-to):")?)? {
+        query = query.shelves(from, to);
+    }
+    if let Some((from, to)) = parse_range(&ask_line("Zones (from-to):")?)? {
+        query = query.zones(from, to);
+    }
+
+    let sort = ask_line(
+        "Sort by (0: Id, 1: Name, 2: Quantity, 3: Expiration, 4: Position, add '-' for descending e.g. 2-):",
+    )?;
+    let (key, descending) = match sort.strip_suffix('-') {
+        Some(key) => (key, true),
+        None => (sort.as_str(), false),
+    };
+    let key = match key {
+        "" => None,
+        "0" => Some(SortKey::Id),
+        "1" => Some(SortKey::Name),
+        "2" => Some(SortKey::Quantity),
+        "3" => Some(SortKey::Expiration),
+        "4" => Some(SortKey::Position),
+        _ => return Err(MyError::WrongOption(sort.clone())),
+    };
+    query = match (key, descending) {
+        (Some(key), false) => query.sort_by(key),
+        (Some(key), true) => query.sort_by_descending(key),
+        (None, _) => query,
     };
 
-    println!("Name:");
-    let mut input_name: String = String::new();
-    let result = std::io::stdin().read_line(&mut input_name);
-    if let Err(err) = result {
-        return Err(MyError::IOError(err));
+    let limit = ask_line("Max results:")?;
+    if !limit.is_empty() {
+        query = query.limit(limit.parse::<usize>().map_err(MyError::ParseIntError)?);
+    }
+
+    Ok(query)
+}
+
+fn ask_heatmap_export(placement: &Placement) -> Result<String, MyError> {
+    let path = ask_line("File to write (.html for all views, .svg for a single view):")?;
+    let today = parse_date(&ask_line("Today's date as xx-xx-xxxx:")?)?;
+
+    let mode = if path.to_lowercase().ends_with(".svg") {
+        match ask_line("Colour by:\n0: Occupancy\n1: Quality\n2: Days to expiry")?.as_str() {
+            "0" => HeatmapMode::Occupancy,
+            "1" => HeatmapMode::Quality,
+            "2" => HeatmapMode::Expiry,
+            other => return Err(MyError::WrongOption(other.to_string())),
+        }
+    } else {
+        HeatmapMode::Occupancy
     };
 
-    println!("Quantity:");
-    let mut input_quantity: String = String::new();
-    let result = std::io::stdin().read_line(&mut input_quantity);
-    if let Err(err) = result {
-        return Err(MyError::IOError(err));
+    placement.export_heatmap(std::path::Path::new(&path), mode, today)?;
+    Ok(path)
+}
+
+// "1, 2,3" -> [1, 2, 3]
+fn parse_numbers(input: &str) -> Result<Vec<u32>, MyError> {
+    input
+        .split(',')
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+        .map(|s| s.parse::<u32>().map_err(MyError::ParseIntError))
+        .collect()
+}
+
+fn ask_pick_route(placement: &Placement) -> Result<Route, MyError> {
+    let ids = parse_numbers(&ask_line("Item ids to pick (comma separated):")?)?;
+
+    let dock = match parse_numbers(&ask_line("Dock position as row,shelf,zone (empty for 0,0,0):")?)?[..] {
+        [] => Position::new(0, 0, 0),
+        [row, shelf, zone] => Position::new(row, shelf, zone),
+        _ => return Err(MyError::WrongOption("dock needs row,shelf,zone".to_string())),
+    };
+
+    let default = TravelCost::default();
+    let prompt = format!(
+        "Cost per row,shelf,zone step (empty for {},{},{}):",
+        default.row, default.shelf, default.zone
+    );
+    let cost = match parse_numbers(&ask_line(&prompt)?)?[..] {
+        [] => default,
+        [row, shelf, zone] => TravelCost { row, shelf, zone },
+        _ => return Err(MyError::WrongOption("cost needs row,shelf,zone".to_string())),
+    };
+
+    placement.pick_route(&ids, dock, &cost)
+}
+
+fn ask_order() -> Result<Order, MyError> {
+    let id = ask_line("Order id:")?
+        .parse::<u32>()
+        .map_err(MyError::ParseIntError)?;
+
+    let mut lines = Vec::new();
+    loop {
+        let item = ask_line("Item name or #id (empty to finish):")?;
+        if item.is_empty() {
+            break;
+        }
+        let item = match item.strip_prefix('#') {
+            Some(id) => ItemRef::Id(id.trim().parse::<u32>().map_err(MyError::ParseIntError)?),
+            None => ItemRef::Name(item),
+        };
+        let quantity = ask_line("Quantity:")?
+            .parse::<u32>()
+            .map_err(MyError::ParseIntError)?;
+        lines.push(OrderLine { item, quantity });
     }
-    input_quantity = input_quantity.trim().to_string();
+    Ok(Order { id, lines })
+}
 
-    let result = input_quantity.parse::<u32>();
-    let input_quantity = match result {
-        Ok(duration) => duration,
-        Err(err) => return Err(MyError::ParseIntError(err)),
+// new warehouse with its own layout and filters
+fn ask_warehouse() -> Result<Placement, MyError> {
+    let default = Layout::default();
+    let prompt = format!(
+        "Layout as rows,shelves,zones (empty for {},{},{}):",
+        default.rows, default.shelves, default.zones
+    );
+    let layout = match parse_numbers(&ask_line(&prompt)?)?[..] {
+        [] => default,
+        [rows, shelves, zones] => Layout::new(rows, shelves, zones),
+        _ => return Err(MyError::WrongOption("layout needs rows,shelves,zones".to_string())),
+    };
+    if layout.cells().is_none() {
+        let message = format!("a layout may have at most {} positions", MAXCELLS);
+        return Err(MyError::WrongOption(message));
+    }
+
+    let mut filters = Vec::<Box<dyn Filter>>::new();
+    let cutoff = ask_line("Largest oversized item allowed (empty for no limit):")?;
+    if !cutoff.is_empty() {
+        let cutoff = cutoff.parse::<u32>().map_err(MyError::ParseIntError)?;
+        filters.push(Box::from(AvoidTooLarge { cutoff }));
+    }
+    let cutoff = ask_line("Smallest max row allowed for fragile items (empty for no limit):")?;
+    if !cutoff.is_empty() {
+        let cutoff = cutoff.parse::<u32>().map_err(MyError::ParseIntError)?;
+        filters.push(Box::from(AvoidTooFragile { cutoff }));
+    }
+
+    let mut placement = Placement::with_layout(layout);
+    placement.configure_strategy(Box::from(RoundRobin {}));
+    placement.configure_filters(filters);
+    Ok(placement)
+}
+
+fn ask_temperature_zone(placement: &mut Placement) -> Result<(), MyError> {
+    let row = ask_line("Row:")?.parse::<u32>().map_err(MyError::ParseIntError)?;
+    let shelf = ask_line("Shelf (empty for the whole row):")?;
+    let shelf = match shelf.as_str() {
+        "" => None,
+        shelf => Some(shelf.parse::<u32>().map_err(MyError::ParseIntError)?),
+    };
+    let range = ask_line("Temperature as min,max in °C (empty to clear):")?;
+    let zone = match range.split(',').map(|s| s.trim()).collect::<Vec<&str>>()[..] {
+        [""] => None,
+        [min, max] => Some(TemperatureZone {
+            min: min.parse::<i32>().map_err(MyError::ParseIntError)?,
+            max: max.parse::<i32>().map_err(MyError::ParseIntError)?,
+        }),
+        _ => return Err(MyError::WrongOption(range)),
     };
+    placement.set_temperature_zone(row, shelf, zone);
+    Ok(())
+}
 
+fn ask_segregation(placement: &mut Placement) -> Result<(), MyError> {
+    match parse_numbers(&ask_line("Hazard classes and minimum distance as a,b,distance:")?)?[..] {
+        [a, b, distance] => {
+            placement.set_segregation(a, b, distance);
+            Ok(())
+        }
+        _ => Err(MyError::WrongOption("expected a,b,distance".to_string())),
+    }
+}
+
+fn ask_move(placement: &mut Placement) -> Result<Vec<Position>, MyError> {
+    let id = ask_id()?;
+    match parse_numbers(&ask_line("New position as row,shelf,zone:")?)?[..] {
+        [row, shelf, zone] => placement.move_item(id, Position::new(row, shelf, zone)),
+        _ => Err(MyError::WrongOption("position needs row,shelf,zone".to_string())),
+    }
+}
+
+fn ask_transfer(warehouses: &mut Registry) -> Result<Vec<Position>, MyError> {
+    let id = ask_id()?;
+    let from = ask_line("From warehouse:")?;
+    let to = ask_line("To warehouse:")?;
+    warehouses.transfer(id, &from, &to)
+}
+
+fn ask_quality() -> Result<Quality, MyError> {
     println!(
         "Quality:\n\
               0: Fragile\n\
               1: Oversized\n\
-              2: Normal"
+              2: Normal\n\
+              3: Refrigerated\n\
+              4: Hazardous"
     );
     let mut input_quality: String = String::new();
     let result = std::io::stdin().read_line(&mut input_quality);
@@ -561,7 +332,7 @@ fn ask_new_product() -> Result<Item, MyError> {
         return Err(MyError::IOError(err));
     };
 
-    let product_quality = match input_quality.trim() {
+    match input_quality.trim() {
         "0" => {
             println!("Insert expiration date as xx-xx-xxxx");
 
@@ -623,10 +394,168 @@ fn ask_new_product() -> Result<Item, MyError> {
             })
         }
         "2" => Ok(Quality::Normal),
+        "3" => {
+            let min_temp = ask_line("Lowest temperature in °C")?;
+            let min_temp = min_temp.parse::<i32>().map_err(MyError::ParseIntError)?;
+            let max_temp = ask_line("Highest temperature in °C")?;
+            let max_temp = max_temp.parse::<i32>().map_err(MyError::ParseIntError)?;
+            Ok(Quality::Refrigerated { min_temp, max_temp })
+        }
+        "4" => {
+            let class = ask_line("Hazard class")?;
+            let class = class.parse::<u32>().map_err(MyError::ParseIntError)?;
+            Ok(Quality::Hazardous { class })
+        }
         _ => Err(MyError::WrongOption(input_quality.trim().to_string())),
+    }
+}
+
+fn ask_product() -> Result<Product, MyError> {
+    let sku = ask_line("SKU:")?;
+    let name = ask_name()?;
+    let default_quality = ask_quality()?;
+    Ok(Product {
+        sku,
+        name,
+        default_quality,
+    })
+}
+
+fn ask_lot(placement: &mut Placement) -> Result<u32, MyError> {
+    let sku = ask_line("SKU:")?;
+    let perishable = match placement.catalog().product(&sku) {
+        Some(product) => matches!(product.default_quality, Quality::Fragile { .. }),
+        None => return Err(MyError::UnknownSku(sku)),
     };
+    let lot_number = ask_line("Lot number:")?;
+    let id = match ask_line("Id (empty to generate one):")?.as_str() {
+        "" => None,
+        id => Some(id.parse::<u32>().map_err(MyError::ParseIntError)?),
+    };
+    let quantity = ask_line("Quantity:")?
+        .parse::<u32>()
+        .map_err(MyError::ParseIntError)?;
+    let expiration_date = match perishable {
+        true => Some(ask_expiration_date()?),
+        false => None,
+    };
+    let intake_date = parse_date(&ask_line("Received on (xx-xx-xxxx):")?)?;
+    placement.receive_lot(&sku, &lot_number, id, quantity, expiration_date, intake_date)
+}
+
+fn ask_reservation(placement: &mut Placement) -> Result<Vec<Position>, MyError> {
+    let asn = ask_line("Delivery (ASN):")?;
+    let name = ask_name()?;
+    let quality = ask_quality()?;
+    let expires = parse_date(&ask_line("Hold until (xx-xx-xxxx):")?)?;
+    placement.reserve(&asn, &name, quality, expires)
+}
+
+fn ask_delivery(placement: &mut Placement) -> Result<u32, MyError> {
+    let asn = ask_line("Delivery (ASN):")?;
+    let item = ask_new_product(placement.next_free_id())?;
+    placement.receive_delivery(&asn, item)
+}
+
+fn ask_reorder_rule(placement: &mut Placement) -> Result<(), MyError> {
+    let name = ask_name()?;
+    match parse_numbers(&ask_line("Reorder point and target as point,target (empty to remove):")?)?[..] {
+        [] => {
+            placement.remove_reorder_rule(&name);
+            Ok(())
+        }
+        [reorder_point, target] => placement.set_reorder_rule(
+            &name,
+            ReorderRule {
+                reorder_point,
+                target,
+            },
+        ),
+        _ => Err(MyError::WrongOption("expected point,target".to_string())),
+    }
+}
 
-    let item = Item {
+// warns about every name that the last consumption left below its reorder point
+fn flag_low_stock<'a>(placement: &Placement, names: impl IntoIterator<Item = &'a str>) {
+    for name in names.into_iter().unique() {
+        if let Some(line) = placement.needs_reorder(name) {
+            println!("Low stock! {}", line);
+        }
+    }
+}
+
+// seed, days and arrivals per day, everything else keeps its default
+fn ask_simulation() -> Result<SimulationConfig, MyError> {
+    let mut config = SimulationConfig::default();
+    let seed = ask_line(&format!("Seed (empty for {}):", config.seed))?;
+    if !seed.is_empty() {
+        config.seed = seed.parse::<u64>().map_err(MyError::ParseIntError)?;
+    }
+    let days = ask_line(&format!("Days (empty for {}):", config.days))?;
+    if !days.is_empty() {
+        config.days = days.parse::<u32>().map_err(MyError::ParseIntError)?;
+    }
+    let (from, to) = config.arrivals_per_day;
+    let prompt = format!("Arrivals per day, a-b (empty for {}-{}):", from, to);
+    if let Some(arrivals) = parse_range(&ask_line(&prompt)?)? {
+        config.arrivals_per_day = arrivals;
+    }
+    Ok(config)
+}
+
+// runs the consistency checker and prints what it finds
+fn report_violations(placement: &Placement) {
+    let violations = placement.validate();
+    if violations.is_empty() {
+        println!("Placement is consistent");
+    }
+    for violation in violations {
+        println!("Inconsistent: {}", violation);
+    }
+}
+
+// an empty id means the placement picks one, `next_id` is what it would pick
+fn ask_new_product(next_id: u32) -> Result<NewItem, MyError> {
+    println!("Id (empty for {}):", next_id);
+    let mut input_id: String = String::new();
+    let result = std::io::stdin().read_line(&mut input_id);
+    if let Err(err) = result {
+        return Err(MyError::IOError(err));
+    }
+    input_id = input_id.trim().to_string();
+
+    let input_id = match input_id.as_str() {
+        "" => None,
+        _ => match input_id.parse::<u32>() {
+            Ok(id) => Some(id),
+            Err(err) => return Err(MyError::ParseIntError(err)),
+        },
+    };
+
+    println!("Name:");
+    let mut input_name: String = String::new();
+    let result = std::io::stdin().read_line(&mut input_name);
+    if let Err(err) = result {
+        return Err(MyError::IOError(err));
+    };
+
+    println!("Quantity:");
+    let mut input_quantity: String = String::new();
+    let result = std::io::stdin().read_line(&mut input_quantity);
+    if let Err(err) = result {
+        return Err(MyError::IOError(err));
+    }
+    input_quantity = input_quantity.trim().to_string();
+
+    let result = input_quantity.parse::<u32>();
+    let input_quantity = match result {
+        Ok(duration) => duration,
+        Err(err) => return Err(MyError::ParseIntError(err)),
+    };
+
+    let product_quality = ask_quality();
+
+    let item = NewItem {
         id: input_id,
         name: input_name.trim().to_string(),
         quantity: input_quantity,
@@ -645,12 +574,30 @@ fn main() {
     // setup filters
     let filter1 = AvoidTooLarge { cutoff: 3 };  // oversized items must not be larger than cutoff
     let filter2 = AvoidTooFragile { cutoff: 2 }; // fragile items must at least have this much flexibility
-    let mut filters = Vec::<Box<dyn Filter>>::new();
-    filters.push(Box::from(filter1));
-    filters.push(Box::from(filter2));
+    let filters: Vec<Box<dyn Filter>> = vec![Box::from(filter1), Box::from(filter2)];
 
     supermarket.configure_filters(filters);
 
+    // last row is the cold room
+    supermarket.set_temperature_zone(MAXPOSITION - 1, None, Some(TemperatureZone { min: 0, max: 4 }));
+
+    // --log-events: every add, removal, rejection and expiry is also written to stderr
+    let log_events = std::env::args().any(|arg| arg == "--log-events");
+    if log_events {
+        supermarket.add_observer(Box::from(EventLog {
+            label: "supermarket".to_string(),
+        }));
+    }
+
+    // --machine: JSON commands on stdin, JSON answers on stdout, no prompts and no example stock
+    if std::env::args().any(|arg| arg == "--machine") {
+        let stdin = std::io::stdin().lock();
+        if let Err(err) = machine::run(&mut supermarket, stdin, std::io::stdout().lock()) {
+            eprintln!("{}", err);
+        }
+        return;
+    }
+
 
     println!("Booting app....");
 
@@ -688,7 +635,7 @@ fn main() {
         name: "Item5".to_string(),
         quantity: 1,
         quality: Quality::Fragile {
-            expiration_date: [01, 01, 1999],
+            expiration_date: [1, 1, 1999],
             row: 2,
         },
     };
@@ -710,7 +657,7 @@ fn main() {
     println!("Added some example stuff inside the market....");
 
     println!("{}", supermarket);
-
+    report_violations(&supermarket);
 
 
     /*
@@ -739,7 +686,24 @@ fn main() {
     println!("{:#?}", test.check_expired_products([02,02,1999]))
 
      */
+    // --tui: straight into the full-screen view instead of the menu
+    if std::env::args().any(|arg| arg == "--tui") {
+        if let Err(err) = ratatui::run(|terminal| tui::run(terminal, "supermarket", &mut supermarket)) {
+            eprintln!("{}", err);
+        }
+        return;
+    }
+
+    let mut warehouses = Registry::new();
+    let mut current = "supermarket".to_string();
+    warehouses.add(&current, supermarket).unwrap();
+
     loop {
+        let supermarket = warehouses
+            .get_mut(&current)
+            .expect("the current warehouse is never removed");
+
+        println!("Warehouse: {}", current);
         println!(
             "Don't steal\n\
         0: add new item\n\
@@ -749,7 +713,38 @@ fn main() {
         4: get by Name \n\
         5: list positions by ID \n\
         6: list expired :( \n\
-        7: quit"
+        7: quit \n\
+        8: query items \n\
+        9: occupancy statistics \n\
+        10: show grid \n\
+        11: export heatmap \n\
+        12: plan pick route \n\
+        13: place order \n\
+        14: confirm order \n\
+        15: cancel order \n\
+        16: retry backorder \n\
+        17: show orders \n\
+        18: switch or create warehouse \n\
+        19: search all warehouses \n\
+        20: transfer item between warehouses \n\
+        21: set temperature zone \n\
+        22: set hazard segregation \n\
+        23: move item \n\
+        24: consume by name (first expired, first out) \n\
+        25: register product \n\
+        26: receive lot \n\
+        27: stock per SKU \n\
+        28: run simulation \n\
+        29: check consistency \n\
+        30: serve HTTP API \n\
+        31: full-screen view \n\
+        32: reserve for delivery \n\
+        33: receive delivery \n\
+        34: show reservations \n\
+        35: expire reservations \n\
+        36: set reorder point \n\
+        37: replenishment report \n\
+        38: cancel reservation"
         );
 
         let mut option: String = String::new();
@@ -757,16 +752,16 @@ fn main() {
 
         match option.trim() {
             "0" => {
-                let new_item = match ask_new_product() {
+                let new_item = match ask_new_product(supermarket.next_free_id()) {
                     Ok(item) => item,
                     Err(err) => {
                         println!("{}", err);
                         continue;
                     }
                 };
-                if let Err(err) = supermarket.add_item(new_item) {
-                    println!("{}", err);
-                    continue;
+                match supermarket.add_new_item(new_item) {
+                    Ok(id) => println!("Added item with Id {}", id),
+                    Err(err) => println!("{}", err),
                 }
             }
             "1" => {
@@ -846,6 +841,7 @@ fn main() {
                 match result {
                     Ok(expiration_date) => {
                         let maybe_list = supermarket.check_expired_products(expiration_date);
+                        supermarket.notify_expired(expiration_date);
                         match maybe_list {
                             Some(list) => {
                                 for x in list {
@@ -864,9 +860,323 @@ fn main() {
                 }
             }
             "7" => break,
+            "8" => {
+                let query = match ask_query() {
+                    Ok(query) => query,
+                    Err(err) => {
+                        println!("{}", err);
+                        continue;
+                    }
+                };
+                let list = supermarket.query(&query);
+                if list.is_empty() {
+                    println!("No items match the query");
+                }
+                for x in list {
+                    println!("{}", x);
+                }
+            }
+            "9" => println!("{}", supermarket.stats()),
+            "10" => {
+                let rows = match ask_line("Row (empty for all rows):") {
+                    Ok(input) if input.is_empty() => supermarket.rows().into_iter().collect(),
+                    Ok(input) => match input.parse::<u32>() {
+                        Ok(row) => vec![row],
+                        Err(err) => {
+                            println!("{}", MyError::ParseIntError(err));
+                            continue;
+                        }
+                    },
+                    Err(err) => {
+                        println!("{}", err);
+                        continue;
+                    }
+                };
+                for row in rows {
+                    println!("{}", supermarket.render_row(row));
+                }
+                println!("{}", GRID_LEGEND);
+            }
+            "11" => match ask_heatmap_export(supermarket) {
+                Ok(path) => println!("Heatmap written to {}", path),
+                Err(err) => println!("{}", err),
+            },
+            "12" => match ask_pick_route(supermarket) {
+                Ok(route) => println!("{}", route),
+                Err(err) => println!("{}", err),
+            },
+            "13" => {
+                let result = ask_order().and_then(|order| supermarket.place_order(order));
+                match result {
+                    Ok(list) => print!("{}", list),
+                    Err(err) => println!("{}", err),
+                }
+            }
+            "14" => {
+                let result = ask_id().and_then(|id| supermarket.confirm_order(id));
+                match result {
+                    Ok(list) => {
+                        print!("Shipped {}", list);
+                        flag_low_stock(supermarket, list.picks.iter().map(|pick| pick.name.as_str()));
+                    }
+                    Err(err) => println!("{}", err),
+                }
+            }
+            "15" => {
+                let result = ask_id().and_then(|id| supermarket.cancel_order(id));
+                if let Err(err) = result {
+                    println!("{}", err);
+                }
+            }
+            "16" => {
+                let result = ask_id().and_then(|id| supermarket.retry_backorder(id));
+                match result {
+                    Ok(list) => print!("{}", list),
+                    Err(err) => println!("{}", err),
+                }
+            }
+            "17" => {
+                let id = match ask_line("Order id (empty for all orders):") {
+                    Ok(id) => id,
+                    Err(err) => {
+                        println!("{}", err);
+                        continue;
+                    }
+                };
+                if !id.is_empty() {
+                    let result = id
+                        .parse::<u32>()
+                        .map_err(MyError::ParseIntError)
+                        .and_then(|id| supermarket.pick_list(id));
+                    match result {
+                        Ok(list) => print!("{}", list),
+                        Err(err) => println!("{}", err),
+                    }
+                    continue;
+                }
+                for list in supermarket.orders().open_orders() {
+                    print!("{}", list);
+                }
+                for order in supermarket.orders().backorders() {
+                    println!("Backorder {}", order.id);
+                    for line in &order.lines {
+                        println!("  {} x {}", line.quantity, line.item);
+                    }
+                }
+            }
+            "18" => {
+                println!("Warehouses: {}", warehouses.names().join(", "));
+                let name = match ask_line("Warehouse name (a new name creates it):") {
+                    Ok(name) if !name.is_empty() => name,
+                    Ok(_) => continue,
+                    Err(err) => {
+                        println!("{}", err);
+                        continue;
+                    }
+                };
+                if warehouses.get(&name).is_err() {
+                    let result = ask_warehouse().and_then(|mut placement| {
+                        if log_events {
+                            placement.add_observer(Box::from(EventLog {
+                                label: name.clone(),
+                            }));
+                        }
+                        warehouses.add(&name, placement)
+                    });
+                    if let Err(err) = result {
+                        println!("{}", err);
+                        continue;
+                    }
+                }
+                current = name;
+            }
+            "19" => {
+                let found = match ask_line("Item name or #id:") {
+                    Ok(input) => match input.strip_prefix('#') {
+                        Some(id) => match id.trim().parse::<u32>() {
+                            Ok(id) => warehouses.find_by_id(id),
+                            Err(err) => {
+                                println!("{}", MyError::ParseIntError(err));
+                                continue;
+                            }
+                        },
+                        None => warehouses.find_by_name(&input),
+                    },
+                    Err(err) => {
+                        println!("{}", err);
+                        continue;
+                    }
+                };
+                if found.is_empty() {
+                    println!("No warehouse has a matching item");
+                }
+                for (name, item) in found {
+                    println!("{}: {}", name, item);
+                }
+            }
+            "20" => match ask_transfer(&mut warehouses) {
+                Ok(positions) => {
+                    let positions = positions.iter().map(|pos| pos.to_string()).join(", ");
+                    println!("Item moved to {}", positions);
+                }
+                Err(err) => println!("{}", err),
+            },
+            "21" => {
+                if let Err(err) = ask_temperature_zone(supermarket) {
+                    println!("{}", err);
+                }
+            }
+            "22" => {
+                if let Err(err) = ask_segregation(supermarket) {
+                    println!("{}", err);
+                }
+            }
+            "23" => match ask_move(supermarket) {
+                Ok(positions) => {
+                    let positions = positions.iter().map(|pos| pos.to_string()).join(", ");
+                    println!("Item moved to {}", positions);
+                }
+                Err(err) => println!("{}", err),
+            },
+            "24" => {
+                let result = ask_name().and_then(|name| {
+                    let quantity = ask_line("Quantity:")?
+                        .parse::<u32>()
+                        .map_err(MyError::ParseIntError)?;
+                    supermarket.consume(&name, quantity)
+                });
+                match result {
+                    Ok(picks) => {
+                        for pick in &picks {
+                            let position = pick
+                                .position
+                                .map_or("no position".to_string(), |pos| pos.to_string());
+                            println!("took {} from item {} at {}", pick.quantity, pick.item_id, position);
+                        }
+                        flag_low_stock(supermarket, picks.iter().map(|pick| pick.name.as_str()));
+                    }
+                    Err(err) => println!("{}", err),
+                }
+            }
+            "25" => {
+                let result = ask_product().and_then(|product| supermarket.register_product(product));
+                if let Err(err) = result {
+                    println!("{}", err);
+                }
+            }
+            "26" => match ask_lot(supermarket) {
+                Ok(id) => {
+                    let positions = supermarket.position_search(id).unwrap_or_default();
+                    let positions = positions.iter().map(|pos| pos.to_string()).join(", ");
+                    println!("Lot stored as item {} at {}", id, positions);
+                }
+                Err(err) => println!("{}", err),
+            },
+            "27" => {
+                for (sku, total) in supermarket.sku_totals() {
+                    let name = supermarket
+                        .catalog()
+                        .product(&sku)
+                        .map_or("", |product| product.name.as_str());
+                    println!("{} {}: {} in stock", sku, name, total);
+                    for lot in supermarket.lots(&sku) {
+                        println!("  {}", lot);
+                    }
+                }
+            }
+            "28" => match ask_simulation() {
+                // an empty copy of this warehouse's layout, so the real stock is left alone
+                Ok(config) => {
+                    let strategies: [Box<dyn Strategy>; 2] =
+                        [Box::new(RoundRobin {}), Box::new(BestFit {})];
+                    for strategy in strategies {
+                        let layout = supermarket.layout().clone();
+                        println!("{}\n", simulate::simulate(&config, layout, strategy));
+                    }
+                }
+                Err(err) => println!("{}", err),
+            },
+            "29" => report_violations(supermarket),
+            "30" => {
+                let result = ask_line("Address (empty for 127.0.0.1:8080):").and_then(|address| {
+                    let address = match address.as_str() {
+                        "" => "127.0.0.1:8080".to_string(),
+                        _ => address,
+                    };
+                    println!("Serving {} on http://{}, POST /shutdown to stop", current, address);
+                    // the menu waits until the server stops, so the server is the only user meanwhile
+                    let shared = SharedPlacement::new(std::mem::take(supermarket));
+                    let result = server::serve(&shared, &address);
+                    *supermarket = shared
+                        .into_inner()
+                        .expect("connection threads are joined before serve returns");
+                    result
+                });
+                if let Err(err) = result {
+                    println!("{}", err);
+                }
+            }
+            "31" => {
+                if let Err(err) = ratatui::run(|terminal| tui::run(terminal, &current, supermarket)) {
+                    println!("{}", err);
+                }
+            }
+            "32" => match ask_reservation(supermarket) {
+                Ok(positions) => {
+                    let positions = positions.iter().map(|pos| pos.to_string()).join(", ");
+                    println!("Reserved {}", positions);
+                }
+                Err(err) => println!("{}", err),
+            },
+            "33" => match ask_delivery(supermarket) {
+                Ok(id) => {
+                    let positions = supermarket.position_search(id).unwrap_or_default();
+                    let positions = positions.iter().map(|pos| pos.to_string()).join(", ");
+                    println!("Delivery stored as item {} at {}", id, positions);
+                }
+                Err(err) => println!("{}", err),
+            },
+            "34" => {
+                let mut empty = true;
+                for reservation in supermarket.reservations() {
+                    println!("{}", reservation);
+                    empty = false;
+                }
+                if empty {
+                    println!("No reservations");
+                }
+            }
+            "35" => match ask_line("Today (xx-xx-xxxx):").and_then(|today| parse_date(&today)) {
+                Ok(today) => {
+                    for reservation in supermarket.expire_reservations(today) {
+                        println!("Expired {}", reservation);
+                    }
+                }
+                Err(err) => println!("{}", err),
+            },
+            "36" => {
+                if let Err(err) = ask_reorder_rule(supermarket) {
+                    println!("{}", err);
+                }
+            }
+            "37" => {
+                let report = supermarket.replenishment_report();
+                if report.is_empty() {
+                    println!("Nothing to reorder");
+                }
+                for line in report {
+                    println!("{}", line);
+                }
+            }
+            "38" => match ask_line("Delivery (ASN):")
+                .and_then(|asn| supermarket.cancel_reservation(&asn))
+            {
+                Ok(reservation) => println!("Cancelled {}", reservation),
+                Err(err) => println!("{}", err),
+            },
             _ => {
-                let err: Result<(), MyError> = Err(MyError::WrongOption(option.trim().to_string()));
-                println!("{:?}", err.unwrap_err());
+                let err = MyError::WrongOption(option.trim().to_string());
+                println!("{:?}", err);
             }
         };
     }
@@ -0,0 +1,363 @@
+use crate::{MyError, Placement, Position};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Display, Formatter};
+
+// What an order line asks for: one specific item, or any stock with that name
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ItemRef {
+    Id(u32),
+    Name(String),
+}
+
+impl Display for ItemRef {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ItemRef::Id(id) => write!(f, "#{}", id),
+            ItemRef::Name(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct OrderLine {
+    pub item: ItemRef,
+    pub quantity: u32,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Order {
+    pub id: u32,
+    pub lines: Vec<OrderLine>,
+}
+
+// One stop of a pick list: take `quantity` of item `item_id` from `position`
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Pick {
+    pub item_id: u32,
+    pub name: String,
+    pub quantity: u32,
+    pub position: Option<Position>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PickList {
+    pub order_id: u32,
+    pub picks: Vec<Pick>,
+    pub backordered: Vec<OrderLine>, // what could not be reserved
+}
+
+impl Display for PickList {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Order {}", self.order_id)?;
+        if self.picks.is_empty() {
+            writeln!(f, "  nothing to pick")?;
+        }
+        for pick in &self.picks {
+            let position = pick
+                .position
+                .map_or("no position".to_string(), |pos| pos.to_string());
+            writeln!(
+                f,
+                "  pick {} x {} (id {}) at {}",
+                pick.quantity, pick.name, pick.item_id, position
+            )?;
+        }
+        for line in &self.backordered {
+            writeln!(f, "  backordered: {} x {}", line.quantity, line.item)?;
+        }
+        Ok(())
+    }
+}
+
+// Open orders and the stock they hold on to
+#[derive(Debug, Default)]
+pub struct OrderBook {
+    open: BTreeMap<u32, PickList>,
+    reserved: HashMap<u32, u32>, // item id -> quantity reserved by open orders
+    backorders: BTreeMap<u32, Order>,
+}
+
+impl OrderBook {
+    pub fn open_orders(&self) -> impl Iterator<Item = &PickList> {
+        self.open.values()
+    }
+
+    pub fn backorders(&self) -> impl Iterator<Item = &Order> {
+        self.backorders.values()
+    }
+
+    fn release(&mut self, list: &PickList) {
+        for pick in &list.picks {
+            if let Some(reserved) = self.reserved.get_mut(&pick.item_id) {
+                *reserved = reserved.saturating_sub(pick.quantity);
+                if *reserved == 0 {
+                    self.reserved.remove(&pick.item_id);
+                }
+            }
+        }
+    }
+}
+
+impl Placement {
+    pub fn orders(&self) -> &OrderBook {
+        &self.orders
+    }
+
+    // quantity of the item that no open order has reserved yet
+    pub fn available_quantity(&self, id: u32) -> u32 {
+        let stored = self.storage.items.get(&id).map_or(0, |item| item.quantity);
+        stored.saturating_sub(self.orders.reserved.get(&id).copied().unwrap_or(0))
+    }
+
+    // ids that can serve the line, in the order stock should be drawn from them (see fefo_ids)
+    fn candidates(&self, item: &ItemRef) -> Vec<u32> {
+        match item {
+            ItemRef::Id(id) => vec![*id],
+            ItemRef::Name(name) => self.fefo_ids(name),
+        }
+    }
+
+    // Reserves stock for every line, splitting a line over several items if needed.
+    // Whatever is not in stock ends up in `backordered` instead of failing the order,
+    // but a line asking for an id that is not stored fails it before anything is reserved.
+    pub fn place_order(&mut self, order: Order) -> Result<PickList, MyError> {
+        if self.orders.open.contains_key(&order.id) || self.orders.backorders.contains_key(&order.id)
+        {
+            return Err(MyError::DuplicateOrder(order.id));
+        }
+        for line in &order.lines {
+            if let ItemRef::Id(id) = line.item
+                && !self.storage.items.contains_key(&id)
+            {
+                return Err(MyError::UnknownId(id));
+            }
+        }
+
+        let mut list = PickList {
+            order_id: order.id,
+            ..PickList::default()
+        };
+        for line in order.lines {
+            let mut missing = line.quantity;
+            for id in self.candidates(&line.item) {
+                if missing == 0 {
+                    break;
+                }
+                let take = self.available_quantity(id).min(missing);
+                if take == 0 {
+                    continue;
+                }
+                missing -= take;
+                *self.orders.reserved.entry(id).or_insert(0) += take;
+                list.picks.push(Pick {
+                    item_id: id,
+                    name: self.storage.items[&id].name.clone(),
+                    quantity: take,
+                    position: self.position_map.get(&id).and_then(|v| v.first().copied()),
+                });
+            }
+            if missing > 0 {
+                list.backordered.push(OrderLine {
+                    item: line.item,
+                    quantity: missing,
+                });
+            }
+        }
+
+        self.orders.open.insert(order.id, list.clone());
+        Ok(list)
+    }
+
+    pub fn pick_list(&self, order_id: u32) -> Result<&PickList, MyError> {
+        self.orders
+            .open
+            .get(&order_id)
+            .ok_or(MyError::UnknownOrder(order_id))
+    }
+
+    // The picks were taken out: decrement the items, removing the ones that run out.
+    // Backordered lines are kept as a backorder under the same order id.
+    pub fn confirm_order(&mut self, order_id: u32) -> Result<PickList, MyError> {
+        let mut list = self
+            .orders
+            .open
+            .remove(&order_id)
+            .ok_or(MyError::UnknownOrder(order_id))?;
+        self.orders.release(&list);
+
+        let mut shipped = Vec::new();
+        for mut pick in list.picks {
+            // the item may have been changed by hand since it was reserved
+            let stored = self.storage.items.get(&pick.item_id).map_or(0, |item| item.quantity);
+            let taken = pick.quantity.min(stored);
+            if taken < pick.quantity {
+                // once the item is gone only its name can still be backordered
+                let item = match self.storage.items.contains_key(&pick.item_id) {
+                    true => ItemRef::Id(pick.item_id),
+                    false => ItemRef::Name(pick.name.clone()),
+                };
+                list.backordered.push(OrderLine {
+                    item,
+                    quantity: pick.quantity - taken,
+                });
+            }
+            if taken == 0 {
+                continue;
+            }
+            if taken == stored {
+                self.remove_item(pick.item_id)?;
+            } else {
+                self.set_quantity(pick.item_id, stored - taken);
+            }
+            pick.quantity = taken;
+            shipped.push(pick);
+        }
+        list.picks = shipped;
+
+        if !list.backordered.is_empty() {
+            self.orders.backorders.insert(
+                order_id,
+                Order {
+                    id: order_id,
+                    lines: list.backordered.clone(),
+                },
+            );
+        }
+        Ok(list)
+    }
+
+    // drops the order and frees its reservations
+    pub fn cancel_order(&mut self, order_id: u32) -> Result<(), MyError> {
+        if self.orders.backorders.remove(&order_id).is_some() {
+            return Ok(());
+        }
+        let list = self
+            .orders
+            .open
+            .remove(&order_id)
+            .ok_or(MyError::UnknownOrder(order_id))?;
+        self.orders.release(&list);
+        Ok(())
+    }
+
+    // tries to reserve a backorder again, e.g. after new stock arrived.
+    // If it cannot be placed it stays a backorder.
+    pub fn retry_backorder(&mut self, order_id: u32) -> Result<PickList, MyError> {
+        let order = self
+            .orders
+            .backorders
+            .remove(&order_id)
+            .ok_or(MyError::UnknownOrder(order_id))?;
+        self.place_order(order.clone()).inspect_err(|_| {
+            self.orders.backorders.insert(order_id, order);
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Item, Layout, Quality};
+
+    fn stocked() -> Placement {
+        let mut placement = Placement::with_layout(Layout::new(1, 1, 10));
+        for (id, name, quantity) in [(1, "Milk", 5), (2, "Milk", 3), (3, "Bread", 2)] {
+            placement
+                .add_item(Item {
+                    id,
+                    name: name.to_string(),
+                    quantity,
+                    quality: Quality::Normal,
+                })
+                .unwrap();
+        }
+        placement
+    }
+
+    fn line(item: ItemRef, quantity: u32) -> OrderLine {
+        OrderLine { item, quantity }
+    }
+
+    #[test]
+    fn place_order_reserves_stock_and_backorders_the_rest() {
+        let mut placement = stocked();
+        let order = Order {
+            id: 10,
+            lines: vec![
+                line(ItemRef::Name("Milk".to_string()), 6),
+                line(ItemRef::Id(3), 4),
+            ],
+        };
+        let list = placement.place_order(order).unwrap();
+
+        let picked: Vec<(u32, u32)> = list
+            .picks
+            .iter()
+            .map(|pick| (pick.item_id, pick.quantity))
+            .collect();
+        assert_eq!(picked, vec![(1, 5), (2, 1), (3, 2)]);
+        assert_eq!(list.backordered, vec![line(ItemRef::Id(3), 2)]);
+        assert_eq!(placement.available_quantity(1), 0);
+        assert_eq!(placement.available_quantity(2), 2);
+        // reserved stock stays where it is until the order is confirmed
+        assert_eq!(placement.storage.items[&1].quantity, 5);
+        assert_eq!(placement.remove_item(1).unwrap_err().kind(), "ItemReserved");
+    }
+
+    #[test]
+    fn place_order_refuses_unknown_ids_and_duplicate_orders() {
+        let mut placement = stocked();
+        let unknown = Order {
+            id: 10,
+            lines: vec![line(ItemRef::Id(1), 1), line(ItemRef::Id(99), 1)],
+        };
+        assert_eq!(placement.place_order(unknown).unwrap_err().kind(), "UnknownId");
+        // nothing was reserved for the line before it
+        assert_eq!(placement.available_quantity(1), 5);
+
+        let order = Order {
+            id: 11,
+            lines: vec![line(ItemRef::Id(1), 1)],
+        };
+        placement.place_order(order.clone()).unwrap();
+        assert_eq!(placement.place_order(order).unwrap_err().kind(), "DuplicateOrder");
+    }
+
+    #[test]
+    fn confirm_order_takes_the_stock_out_and_keeps_the_backorder() {
+        let mut placement = stocked();
+        let order = Order {
+            id: 10,
+            lines: vec![
+                line(ItemRef::Id(1), 2),
+                line(ItemRef::Name("Bread".to_string()), 3),
+            ],
+        };
+        placement.place_order(order).unwrap();
+        let shipped = placement.confirm_order(10).unwrap();
+
+        let picked: Vec<(u32, u32)> = shipped
+            .picks
+            .iter()
+            .map(|pick| (pick.item_id, pick.quantity))
+            .collect();
+        assert_eq!(picked, vec![(1, 2), (3, 2)]);
+        assert_eq!(placement.storage.items[&1].quantity, 3);
+        // the bread ran out, so its cell is free again
+        assert!(!placement.storage.items.contains_key(&3));
+        assert_eq!(placement.available_quantity(1), 3);
+
+        let backorders: Vec<&Order> = placement.orders().backorders().collect();
+        assert_eq!(backorders.len(), 1);
+        assert_eq!(backorders[0].lines, vec![line(ItemRef::Name("Bread".to_string()), 1)]);
+        assert!(placement.pick_list(10).is_err());
+        assert!(placement.validate().is_empty());
+    }
+
+    #[test]
+    fn confirm_order_refuses_an_unknown_order() {
+        let mut placement = stocked();
+        assert_eq!(placement.confirm_order(42).unwrap_err().kind(), "UnknownOrder");
+        assert_eq!(placement.storage.items[&1].quantity, 5);
+    }
+}
@@ -0,0 +1,297 @@
+use crate::{Item, MyError, Placement, Position, Quality};
+use itertools::Itertools;
+use std::cmp::Ordering;
+use std::fmt::{Display, Formatter};
+
+// Quality without its payload, so queries can ask for "all fragile" etc.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub enum QualityKind {
+    Fragile,
+    Oversized,
+    Normal,
+    Refrigerated,
+    Hazardous,
+}
+
+impl QualityKind {
+    pub fn of(quality: &Quality) -> QualityKind {
+        match quality {
+            Quality::Fragile { .. } => QualityKind::Fragile,
+            Quality::Oversized { .. } => QualityKind::Oversized,
+            Quality::Normal => QualityKind::Normal,
+            Quality::Refrigerated { .. } => QualityKind::Refrigerated,
+            Quality::Hazardous { .. } => QualityKind::Hazardous,
+        }
+    }
+}
+
+impl Display for QualityKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QualityKind::Fragile => write!(f, "Fragile"),
+            QualityKind::Oversized => write!(f, "Oversized"),
+            QualityKind::Normal => write!(f, "Normal"),
+            QualityKind::Refrigerated => write!(f, "Refrigerated"),
+            QualityKind::Hazardous => write!(f, "Hazardous"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum SortKey {
+    Id,
+    Name,
+    Quantity,
+    Expiration, // items without an expiration date go last
+    Position,   // first stored position, row then shelf then zone
+}
+
+// A composable query over stored items. Every criterion left as None matches everything,
+// so `ItemQuery::new()` alone lists the whole inventory.
+//
+//     let query = ItemQuery::new()
+//         .quality(QualityKind::Fragile)
+//         .rows(0, 3)
+//         .sort_by(SortKey::Expiration)
+//         .limit(5);
+//     let items = placement.query(&query);
+#[derive(Debug, Clone, Default)]
+pub struct ItemQuery {
+    qualities: Vec<QualityKind>,
+    quantity: (Option<u32>, Option<u32>),
+    expiration: (Option<[u32; 3]>, Option<[u32; 3]>),
+    rows: Option<(u32, u32)>,
+    shelves: Option<(u32, u32)>,
+    zones: Option<(u32, u32)>,
+    sort: Option<(SortKey, bool)>, // bool = descending
+    limit: Option<usize>,
+}
+
+impl ItemQuery {
+    pub fn new() -> ItemQuery {
+        ItemQuery::default()
+    }
+
+    // can be called several times, the item must match any of the given kinds
+    pub fn quality(mut self, kind: QualityKind) -> ItemQuery {
+        self.qualities.push(kind);
+        self
+    }
+
+    pub fn min_quantity(mut self, min: u32) -> ItemQuery {
+        self.quantity.0 = Some(min);
+        self
+    }
+
+    pub fn max_quantity(mut self, max: u32) -> ItemQuery {
+        self.quantity.1 = Some(max);
+        self
+    }
+
+    // dates are [day, month, year] like everywhere else, both bounds inclusive.
+    // Setting any bound drops items that never expire.
+    pub fn expiring_after(mut self, date: [u32; 3]) -> ItemQuery {
+        self.expiration.0 = Some(date);
+        self
+    }
+
+    pub fn expiring_before(mut self, date: [u32; 3]) -> ItemQuery {
+        self.expiration.1 = Some(date);
+        self
+    }
+
+    // region bounds are inclusive; an item matches if any of its positions is inside
+    pub fn rows(mut self, from: u32, to: u32) -> ItemQuery {
+        self.rows = Some((from, to));
+        self
+    }
+
+    pub fn shelves(mut self, from: u32, to: u32) -> ItemQuery {
+        self.shelves = Some((from, to));
+        self
+    }
+
+    pub fn zones(mut self, from: u32, to: u32) -> ItemQuery {
+        self.zones = Some((from, to));
+        self
+    }
+
+    pub fn sort_by(mut self, key: SortKey) -> ItemQuery {
+        self.sort = Some((key, false));
+        self
+    }
+
+    pub fn sort_by_descending(mut self, key: SortKey) -> ItemQuery {
+        self.sort = Some((key, true));
+        self
+    }
+
+    pub fn limit(mut self, limit: usize) -> ItemQuery {
+        self.limit = Some(limit);
+        self
+    }
+
+    fn matches(&self, item: &Item, positions: &[Position]) -> bool {
+        if !self.qualities.is_empty() && !self.qualities.contains(&QualityKind::of(&item.quality))
+        {
+            return false;
+        }
+
+        let (min, max) = self.quantity;
+        if min.is_some_and(|min| item.quantity < min) || max.is_some_and(|max| item.quantity > max)
+        {
+            return false;
+        }
+
+        if self.expiration != (None, None) {
+            let Some(expiration) = expiration_of(item) else {
+                return false;
+            };
+            let (from, to) = self.expiration;
+            if from.is_some_and(|from| date_key(expiration) < date_key(from))
+                || to.is_some_and(|to| date_key(expiration) > date_key(to))
+            {
+                return false;
+            }
+        }
+
+        if self.rows.is_some() || self.shelves.is_some() || self.zones.is_some() {
+            let within = |bounds: Option<(u32, u32)>, value: u32| {
+                bounds.is_none_or(|(from, to)| from <= value && value <= to)
+            };
+            return positions.iter().any(|pos| {
+                within(self.rows, pos.row)
+                    && within(self.shelves, pos.shelf)
+                    && within(self.zones, pos.zone)
+            });
+        }
+
+        true
+    }
+}
+
+fn expiration_of(item: &Item) -> Option<[u32; 3]> {
+    match &item.quality {
+        Quality::Fragile {
+            expiration_date, ..
+        } => Some(*expiration_date),
+        _ => None,
+    }
+}
+
+// "dd-mm-yyyy" -> [day, month, year]
+pub fn parse_date(input: &str) -> Result<[u32; 3], MyError> {
+    let parts: Vec<&str> = input.split('-').map(|s| s.trim()).collect();
+    if parts.len() != 3 {
+        return Err(MyError::InvalidDateFormat(input.to_string()));
+    }
+
+    let day = parts[0].parse::<u32>().map_err(MyError::ParseIntError)?;
+    let month = parts[1].parse::<u32>().map_err(MyError::ParseIntError)?;
+    let year = parts[2].parse::<u32>().map_err(MyError::ParseIntError)?;
+    Ok([day, month, year])
+}
+
+// [day, month, year] -> something that orders chronologically
+pub fn date_key(date: [u32; 3]) -> (u32, u32, u32) {
+    let [day, month, year] = date;
+    (year, month, day)
+}
+
+// [day, month, year] -> days since 1970-01-01, so dates can be subtracted
+pub fn day_number(date: [u32; 3]) -> i64 {
+    let [day, month, year] = date.map(i64::from);
+    // shift the year to start in March so the leap day is the last day of the year
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+// inverse of day_number
+pub fn date_from_day_number(days: i64) -> [u32; 3] {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    [day as u32, month as u32, year as u32]
+}
+
+// `descending` flips the order, except that items without an expiration date stay last
+fn compare(
+    key: SortKey,
+    descending: bool,
+    a: &(Item, Option<Position>),
+    b: &(Item, Option<Position>),
+) -> Ordering {
+    let ordering = match key {
+        SortKey::Id => a.0.id.cmp(&b.0.id),
+        SortKey::Name => Ord::cmp(&a.0.name.to_lowercase(), &b.0.name.to_lowercase()),
+        SortKey::Quantity => a.0.quantity.cmp(&b.0.quantity),
+        SortKey::Expiration => {
+            return match (expiration_of(&a.0), expiration_of(&b.0)) {
+                (Some(x), Some(y)) if descending => date_key(y).cmp(&date_key(x)),
+                (Some(x), Some(y)) => date_key(x).cmp(&date_key(y)),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => Ordering::Equal,
+            };
+        }
+        SortKey::Position => {
+            let a = a.1.map(|p| p.as_tuple());
+            let b = b.1.map(|p| p.as_tuple());
+            a.cmp(&b)
+        }
+    };
+    match descending {
+        true => ordering.reverse(),
+        false => ordering,
+    }
+}
+
+impl Placement {
+    pub fn query(&self, query: &ItemQuery) -> Vec<Item> {
+        // id order first so unsorted results (and ties) are stable between runs
+        let found = self
+            .storage
+            .items
+            .values()
+            .filter(|item| {
+                let positions = self.position_map.get(&item.id).map_or(&[][..], |v| v);
+                query.matches(item, positions)
+            })
+            .map(|item| {
+                let first = self
+                    .position_map
+                    .get(&item.id)
+                    .and_then(|v| v.iter().min_by_key(|p| p.as_tuple()).copied());
+                (item.clone(), first)
+            })
+            .sorted_by_key(|(item, _)| item.id);
+
+        let sorted: Vec<(Item, Option<Position>)> = match query.sort {
+            Some((key, descending)) => found
+                .sorted_by(|a, b| compare(key, descending, a, b))
+                .collect(),
+            None => found.collect(),
+        };
+
+        sorted
+            .into_iter()
+            .map(|(item, _)| item)
+            .take(query.limit.unwrap_or(usize::MAX))
+            .collect()
+    }
+}
@@ -0,0 +1,82 @@
+use crate::{MyError, Placement};
+use std::fmt::{Display, Formatter};
+
+// When to reorder a product and how far to fill it up again: once the stock of the name
+// is below `reorder_point`, enough is ordered to get back to `target`
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct ReorderRule {
+    pub reorder_point: u32,
+    pub target: u32,
+}
+
+// One line of the replenishment report
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Replenishment {
+    pub name: String,
+    pub on_hand: u32,
+    pub rule: ReorderRule,
+    pub order_quantity: u32,
+}
+
+impl Display for Replenishment {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: {} on hand, reorder point {}, order {} to reach {}",
+            self.name, self.on_hand, self.rule.reorder_point, self.order_quantity, self.rule.target
+        )
+    }
+}
+
+impl Placement {
+    // the target has to be at least the reorder point, otherwise reordering would not lift
+    // the stock above it
+    pub fn set_reorder_rule(&mut self, name: &str, rule: ReorderRule) -> Result<(), MyError> {
+        if rule.target < rule.reorder_point {
+            return Err(MyError::WrongOption(format!(
+                "target {} is below reorder point {}",
+                rule.target, rule.reorder_point
+            )));
+        }
+        self.reorder_rules.insert(name.to_string(), rule);
+        Ok(())
+    }
+
+    pub fn remove_reorder_rule(&mut self, name: &str) -> Option<ReorderRule> {
+        self.reorder_rules.remove(name)
+    }
+
+    pub fn reorder_rule(&self, name: &str) -> Option<ReorderRule> {
+        self.reorder_rules.get(name).copied()
+    }
+
+    // total quantity over every item with that name, reserved by orders or not
+    pub fn stock_of(&self, name: &str) -> u32 {
+        self.storage
+            .items
+            .values()
+            .filter(|item| item.name == name)
+            .map(|item| item.quantity)
+            .sum()
+    }
+
+    // what to order for `name`, None if it has no rule or is not below its reorder point
+    pub fn needs_reorder(&self, name: &str) -> Option<Replenishment> {
+        let rule = self.reorder_rule(name)?;
+        let on_hand = self.stock_of(name);
+        (on_hand < rule.reorder_point).then(|| Replenishment {
+            name: name.to_string(),
+            on_hand,
+            rule,
+            order_quantity: rule.target - on_hand,
+        })
+    }
+
+    // every name below its reorder point, by name
+    pub fn replenishment_report(&self) -> Vec<Replenishment> {
+        self.reorder_rules
+            .keys()
+            .filter_map(|name| self.needs_reorder(name))
+            .collect()
+    }
+}
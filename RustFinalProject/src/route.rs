@@ -0,0 +1,148 @@
+use crate::{MyError, Placement, Position};
+use itertools::Itertools;
+use std::fmt::{Display, Formatter};
+
+// Cost of moving one step along each axis. Changing aisle (row) is usually the slowest,
+// reaching another shelf level is next, walking along the shelf (zone) is cheapest.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct TravelCost {
+    pub row: u32,
+    pub shelf: u32,
+    pub zone: u32,
+}
+
+impl Default for TravelCost {
+    fn default() -> TravelCost {
+        TravelCost {
+            row: 10,
+            shelf: 3,
+            zone: 1,
+        }
+    }
+}
+
+impl TravelCost {
+    // weighted manhattan distance, saturating instead of overflowing on huge weights
+    pub fn distance(&self, a: &Position, b: &Position) -> u64 {
+        let step = |from: u32, to: u32, weight: u32| u64::from(from.abs_diff(to)) * u64::from(weight);
+        step(a.row, b.row, self.row)
+            .saturating_add(step(a.shelf, b.shelf, self.shelf))
+            .saturating_add(step(a.zone, b.zone, self.zone))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Route {
+    pub dock: Position,
+    pub stops: Vec<(u32, Position)>, // (item id, where to pick it), in walking order
+    pub total: u64,
+    pub cost: TravelCost,
+}
+
+impl Display for Route {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "start at dock {}", self.dock)?;
+        let mut previous = self.dock;
+        let mut walked: u64 = 0;
+        for (index, (id, pos)) in self.stops.iter().enumerate() {
+            let leg = self.cost.distance(&previous, pos);
+            walked = walked.saturating_add(leg);
+            writeln!(
+                f,
+                "{:>3}. pick item {} at {} (+{}, {} so far)",
+                index + 1,
+                id,
+                pos,
+                leg,
+                walked
+            )?;
+            previous = *pos;
+        }
+        writeln!(
+            f,
+            "back to dock {} (+{})",
+            self.dock,
+            self.cost.distance(&previous, &self.dock)
+        )?;
+        write!(f, "Total distance: {}", self.total)
+    }
+}
+
+fn tour_length(dock: &Position, stops: &[(u32, Position)], cost: &TravelCost) -> u64 {
+    let mut previous = dock;
+    let mut total: u64 = 0;
+    for (_, pos) in stops {
+        total = total.saturating_add(cost.distance(previous, pos));
+        previous = pos;
+    }
+    total.saturating_add(cost.distance(previous, dock))
+}
+
+impl Placement {
+    // Walking order over the given items that starts and ends at `dock`.
+    // Nearest neighbour gives a first tour (for Oversized items the closest of their cells
+    // is used), then 2-opt reverses segments for as long as that makes the tour shorter.
+    pub fn pick_route(
+        &self,
+        ids: &[u32],
+        dock: Position,
+        cost: &TravelCost,
+    ) -> Result<Route, MyError> {
+        if !self.storage.contains(&dock) {
+            return Err(MyError::InvalidRequest(format!("dock {} is outside the layout", dock)));
+        }
+        let mut pending = Vec::new();
+        for id in ids.iter().unique() {
+            match self.position_map.get(id) {
+                Some(positions) if !positions.is_empty() => pending.push((*id, positions)),
+                _ => return Err(MyError::UnknownId(*id)),
+            }
+        }
+
+        let mut stops: Vec<(u32, Position)> = Vec::new();
+        let mut current = dock;
+        while !pending.is_empty() {
+            let (index, pos) = pending
+                .iter()
+                .enumerate()
+                .map(|(index, (_, positions))| {
+                    let closest = positions
+                        .iter()
+                        .min_by_key(|pos| cost.distance(&current, pos))
+                        .copied()
+                        .unwrap_or(current);
+                    (index, closest)
+                })
+                .min_by_key(|(_, pos)| cost.distance(&current, pos))
+                .unwrap_or((0, current));
+            let (id, _) = pending.remove(index);
+            stops.push((id, pos));
+            current = pos;
+        }
+
+        let mut best = tour_length(&dock, &stops, cost);
+        let mut improved = true;
+        while improved {
+            improved = false;
+            for i in 0..stops.len() {
+                for j in (i + 1)..stops.len() {
+                    stops[i..=j].reverse();
+                    let length = tour_length(&dock, &stops, cost);
+                    if length < best {
+                        best = length;
+                        improved = true;
+                    } else {
+                        stops[i..=j].reverse();
+                    }
+                }
+            }
+        }
+
+        Ok(Route {
+            dock,
+            stops,
+            total: best,
+            cost: *cost,
+        })
+    }
+}
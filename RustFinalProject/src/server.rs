@@ -0,0 +1,356 @@
+use crate::query::parse_date;
+use crate::shared::SharedPlacement;
+use crate::{Item, MyError, NewItem, Placement};
+use itertools::Itertools;
+use serde_json::{Value, json};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+const ACCEPT_BACKOFF: Duration = Duration::from_millis(50);
+const MAX_BODY: usize = 1 << 20; // bytes, anything larger is refused before it is read
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Response {
+    pub status: u16,
+    pub body: Option<Value>,
+}
+
+impl Response {
+    fn ok(body: Value) -> Response {
+        Response {
+            status: 200,
+            body: Some(body),
+        }
+    }
+
+    fn not_found(method: &str, path: &str) -> Response {
+        Response {
+            status: 404,
+            body: Some(json!({
+                "error": format!("no endpoint {} {}", method, path),
+                "kind": "NoEndpoint",
+            })),
+        }
+    }
+}
+
+impl From<MyError> for Response {
+    fn from(err: MyError) -> Response {
+        Response {
+            status: status_code(&err),
+            body: Some(json!({ "error": err.to_string(), "kind": err.kind() })),
+        }
+    }
+}
+
+// HTTP status for an error coming out of the placement
+pub fn status_code(err: &MyError) -> u16 {
+    match err {
+        MyError::UnknownId(_)
+        | MyError::UnknownName(_)
+        | MyError::FailedRemove(_)
+        | MyError::UnknownSku(_)
+        | MyError::UnknownOrder(_)
+        | MyError::UnknownWarehouse(_)
+        | MyError::UnknownReservation(_) => 404,
+        MyError::DuplicateId(_)
+        | MyError::DuplicateSku(_)
+        | MyError::DuplicateOrder(_)
+        | MyError::DuplicateWarehouse(_)
+        | MyError::DuplicateReservation(_)
+        | MyError::ItemReserved(_) => 409,
+        MyError::BlockedByFilter(..)
+        | MyError::SegregationViolation { .. }
+        | MyError::InvalidPosition(..)
+        | MyError::InvalidItem(..)
+        | MyError::WrongDelivery(..)
+        | MyError::InsufficientStock { .. } => 422,
+        MyError::ParseIntError(_)
+        | MyError::InvalidDateFormat(_)
+        | MyError::WrongOption(_)
+        | MyError::InvalidRequest(_) => 400,
+        MyError::RequestTooLarge(..) => 413,
+        // the warehouse is full for this item
+        MyError::FailedAllocation(_) => 507,
+        MyError::FailedAdd(_) | MyError::IOError(_) => 500,
+    }
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        204 => "No Content",
+        400 => "Bad Request",
+        404 => "Not Found",
+        409 => "Conflict",
+        413 => "Payload Too Large",
+        422 => "Unprocessable Entity",
+        507 => "Insufficient Storage",
+        _ => "Internal Server Error",
+    }
+}
+
+pub(crate) fn item_json(placement: &Placement, item: &Item) -> Value {
+    json!({
+        "id": item.id,
+        "name": item.name,
+        "quantity": item.quantity,
+        "quality": item.quality,
+        "positions": placement.position_map.get(&item.id).cloned().unwrap_or_default(),
+    })
+}
+
+// "a%20b+c" -> "a b c", bytes that do not decode are kept as they are
+fn decode(input: &str) -> String {
+    let bytes = input.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        match bytes[index] {
+            b'+' => decoded.push(b' '),
+            b'%' => {
+                match input
+                    .get(index + 1..index + 3)
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                {
+                    Some(byte) => {
+                        decoded.push(byte);
+                        index += 2;
+                    }
+                    None => decoded.push(b'%'),
+                }
+            }
+            byte => decoded.push(byte),
+        }
+        index += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn query_param(query: &str, key: &str) -> Option<String> {
+    query.split('&').find_map(|pair| {
+        let (name, value) = pair.split_once('=')?;
+        (name == key).then(|| decode(value))
+    })
+}
+
+fn parse_id(segment: &str) -> Result<u32, MyError> {
+    segment.parse::<u32>().map_err(MyError::ParseIntError)
+}
+
+// path, query string and the non-empty path segments of a request target
+fn route(target: &str) -> (&str, &str, Vec<&str>) {
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let segments = path.split('/').filter(|s| !s.is_empty()).collect();
+    (path, query, segments)
+}
+
+// Answers one request:
+//   GET    /items                 every item, alphabetically
+//   GET    /items?name=<name>     every item with that name, first to leave first
+//   POST   /items                 add a NewItem, 201 with the stored item
+//   GET    /items/<id>            one item
+//   DELETE /items/<id>            remove it, 204
+//   GET    /items/<id>/positions  the cells it covers
+//   GET    /expired?date=<dd-mm-yyyy>
+//   GET    /stats
+pub fn handle(placement: &mut Placement, method: &str, target: &str, body: &str) -> Response {
+    if method == "GET" {
+        return handle_get(placement, target);
+    }
+    let (path, _, segments) = route(target);
+    let result = match (method, segments.as_slice()) {
+        ("POST", ["items"]) => add(placement, body),
+        ("DELETE", ["items", id]) => parse_id(id)
+            .and_then(|id| placement.remove_item(id))
+            .map(|_| Response {
+                status: 204,
+                body: None,
+            }),
+        _ => Ok(Response::not_found(method, path)),
+    };
+    result.unwrap_or_else(Response::from)
+}
+
+// The GET requests of `handle`, which only read the placement
+pub fn handle_get(placement: &Placement, target: &str) -> Response {
+    let (path, query, segments) = route(target);
+    let result = match segments.as_slice() {
+        ["items"] => match query_param(query, "name") {
+            Some(name) => Ok(Response::ok(Value::Array(
+                placement
+                    .fefo_ids(&name)
+                    .iter()
+                    .filter_map(|id| placement.storage.items.get(id))
+                    .map(|item| item_json(placement, item))
+                    .collect(),
+            ))),
+            None => Ok(Response::ok(Value::Array(
+                placement
+                    .alphabetical()
+                    .iter()
+                    .map(|item| item_json(placement, item))
+                    .collect(),
+            ))),
+        },
+        ["items", id] => parse_id(id).and_then(|id| {
+            let item = placement.storage.items.get(&id).ok_or(MyError::UnknownId(id))?;
+            Ok(Response::ok(item_json(placement, item)))
+        }),
+        ["items", id, "positions"] => parse_id(id).and_then(|id| {
+            let positions = placement.position_map.get(&id).ok_or(MyError::UnknownId(id))?;
+            Ok(Response::ok(json!(positions)))
+        }),
+        ["expired"] => query_param(query, "date")
+            .ok_or_else(|| MyError::InvalidRequest("date=dd-mm-yyyy is required".to_string()))
+            .and_then(|date| parse_date(&date))
+            .map(|date| {
+                let expired = placement.check_expired_products(date).unwrap_or_default();
+                Response::ok(Value::Array(
+                    expired
+                        .iter()
+                        .sorted_by_key(|item| item.id)
+                        .map(|item| item_json(placement, item))
+                        .collect(),
+                ))
+            }),
+        ["stats"] => Ok(Response::ok(stats_json(placement))),
+        _ => Ok(Response::not_found("GET", path)),
+    };
+    result.unwrap_or_else(Response::from)
+}
+
+fn add(placement: &mut Placement, body: &str) -> Result<Response, MyError> {
+    let new: NewItem =
+        serde_json::from_str(body).map_err(|err| MyError::InvalidRequest(err.to_string()))?;
+    let id = placement.add_new_item(new)?;
+    Ok(Response {
+        status: 201,
+        body: Some(item_json(placement, &placement.storage.items[&id])),
+    })
+}
+
+pub(crate) fn stats_json(placement: &Placement) -> Value {
+    let stats = placement.stats();
+    let utilisation = |used: u32, total: u32| json!({ "used": used, "total": total });
+    json!({
+        "overall": utilisation(stats.overall.used, stats.overall.total),
+        "per_row": stats
+            .per_row
+            .iter()
+            .map(|(row, u)| (row.to_string(), utilisation(u.used, u.total)))
+            .collect::<serde_json::Map<String, Value>>(),
+        "by_quality": stats
+            .by_quality
+            .iter()
+            .map(|(kind, count)| (kind.to_string(), json!(count)))
+            .collect::<serde_json::Map<String, Value>>(),
+        "largest_free_run": stats.largest_free_run(),
+        "fragmentation": stats.fragmentation(),
+    })
+}
+
+// request line, headers up to the blank line, then Content-Length bytes of body
+fn read_request(stream: &TcpStream) -> Result<(String, String, String), MyError> {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    reader.read_line(&mut line).map_err(MyError::IOError)?;
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Err(MyError::InvalidRequest(format!("bad request line {:?}", line.trim())));
+    };
+    let (method, target) = (method.to_string(), target.to_string());
+
+    let mut length = 0;
+    loop {
+        let mut header = String::new();
+        reader.read_line(&mut header).map_err(MyError::IOError)?;
+        let header = header.trim();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':')
+            && name.eq_ignore_ascii_case("content-length")
+        {
+            length = value.trim().parse::<usize>().map_err(MyError::ParseIntError)?;
+        }
+    }
+    if length > MAX_BODY {
+        return Err(MyError::RequestTooLarge(length, MAX_BODY));
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).map_err(MyError::IOError)?;
+    Ok((method, target, String::from_utf8_lossy(&body).into_owned()))
+}
+
+fn write_response(mut stream: &TcpStream, response: &Response) -> Result<(), MyError> {
+    let body = response
+        .body
+        .as_ref()
+        .map_or(String::new(), |body| body.to_string());
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        response.status,
+        reason(response.status),
+        body.len(),
+        body
+    )
+    .map_err(MyError::IOError)
+}
+
+// Serves the placement over HTTP on `address` (e.g. "127.0.0.1:8080") until a POST /shutdown
+// comes in. Every connection is read and answered on its own thread, so a slow client does
+// not hold up the others; GETs share the placement, changes take it one at a time. Other
+// holders of `shared` keep working on the same warehouse meanwhile.
+// Requests still in flight are answered before this returns.
+pub fn serve(shared: &SharedPlacement, address: &str) -> Result<(), MyError> {
+    let listener = TcpListener::bind(address).map_err(MyError::IOError)?;
+    let local = listener.local_addr().map_err(MyError::IOError)?;
+    let stopping = AtomicBool::new(false);
+    thread::scope(|scope| {
+        for stream in listener.incoming() {
+            if stopping.load(Ordering::SeqCst) {
+                break;
+            }
+            // one failed accept (client gone, out of file handles) does not end the server
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(err) => {
+                    eprintln!("accept failed: {}", err);
+                    thread::sleep(ACCEPT_BACKOFF);
+                    continue;
+                }
+            };
+            let stopping = &stopping;
+            scope.spawn(move || {
+                // a client that never finishes its request gives up its thread after a while
+                let _ = stream.set_read_timeout(Some(READ_TIMEOUT));
+                let response = match read_request(&stream) {
+                    Ok((method, target, _)) if method == "POST" && target == "/shutdown" => {
+                        stopping.store(true, Ordering::SeqCst);
+                        let _ = write_response(&stream, &Response::ok(json!({ "shutdown": true })));
+                        // wake the accept loop up so it sees the flag
+                        let _ = TcpStream::connect(local);
+                        return;
+                    }
+                    Ok((method, target, _)) if method == "GET" => {
+                        shared.read(|placement| handle_get(placement, &target))
+                    }
+                    Ok((method, target, body)) => {
+                        shared.write(|placement| handle(placement, &method, &target, &body))
+                    }
+                    Err(err) => Response::from(err),
+                };
+                // a client that hung up does not stop the server
+                let _ = write_response(&stream, &response);
+            });
+        }
+        Ok(())
+    })
+}
@@ -0,0 +1,126 @@
+use crate::{Item, MyError, NewItem, Placement, Position};
+use std::sync::{Arc, PoisonError, RwLock};
+
+// A Placement several threads (terminals, scanners, HTTP connections) can work on at once.
+// Clones share the same warehouse. Every call holds the lock for its whole duration, so
+// choosing a position and occupying it happen as one step and two concurrent adds can never
+// be given the same cell.
+//
+//     let shared = SharedPlacement::new(placement);
+//     let scanner = shared.clone();
+//     std::thread::spawn(move || scanner.add_item(item));
+#[derive(Debug, Clone, Default)]
+pub struct SharedPlacement {
+    inner: Arc<RwLock<Placement>>,
+}
+
+impl SharedPlacement {
+    pub fn new(placement: Placement) -> SharedPlacement {
+        SharedPlacement {
+            inner: Arc::new(RwLock::new(placement)),
+        }
+    }
+
+    // Runs `f` while no one is changing the placement; readers do not block each other.
+    // A thread that panicked while holding the lock does not lock everyone else out,
+    // `validate` tells whether it left anything half done.
+    pub fn read<R>(&self, f: impl FnOnce(&Placement) -> R) -> R {
+        let placement = self.inner.read().unwrap_or_else(PoisonError::into_inner);
+        f(&placement)
+    }
+
+    // Runs `f` with the placement to itself, for anything that spans several calls
+    // (e.g. check available stock and consume it).
+    pub fn write<R>(&self, f: impl FnOnce(&mut Placement) -> R) -> R {
+        let mut placement = self.inner.write().unwrap_or_else(PoisonError::into_inner);
+        f(&mut placement)
+    }
+
+    pub fn add_item(&self, item: Item) -> Result<(), MyError> {
+        self.write(|placement| placement.add_item(item))
+    }
+
+    // Adds the item, picking an id if it has none, and returns the id
+    pub fn add_new_item(&self, new: NewItem) -> Result<u32, MyError> {
+        self.write(|placement| placement.add_new_item(new))
+    }
+
+    pub fn remove_item(&self, id: u32) -> Result<(), MyError> {
+        self.write(|placement| placement.remove_item(id))
+    }
+
+    // Returns the positions the item covers now
+    pub fn move_item(&self, id: u32, to: Position) -> Result<Vec<Position>, MyError> {
+        self.write(|placement| placement.move_item(id, to))
+    }
+
+    // Copies, since the item may be gone by the time the caller looks at it
+    pub fn item(&self, id: u32) -> Option<Item> {
+        self.read(|placement| placement.storage.items.get(&id).cloned())
+    }
+
+    pub fn positions(&self, id: u32) -> Option<Vec<Position>> {
+        self.read(|placement| placement.position_map.get(&id).cloned())
+    }
+
+    // Gives the placement back once this is the last handle to it
+    pub fn into_inner(self) -> Result<Placement, SharedPlacement> {
+        Arc::try_unwrap(self.inner)
+            .map(|lock| lock.into_inner().unwrap_or_else(PoisonError::into_inner))
+            .map_err(|inner| SharedPlacement { inner })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Layout, Quality};
+    use std::collections::HashSet;
+
+    #[test]
+    fn concurrent_adds_never_share_a_cell() {
+        let shared = SharedPlacement::new(Placement::with_layout(Layout::new(4, 5, 10)));
+        let ids: Vec<u32> = std::thread::scope(|scope| {
+            let workers: Vec<_> = (0..8)
+                .map(|worker| {
+                    let shared = shared.clone();
+                    scope.spawn(move || {
+                        (0..20)
+                            .map(|n| {
+                                let quality = match n % 4 {
+                                    0 => Quality::Oversized {
+                                        continuous_zones: 2,
+                                    },
+                                    _ => Quality::Normal,
+                                };
+                                let new = NewItem {
+                                    id: None,
+                                    name: format!("w{}-{}", worker, n),
+                                    quantity: 1,
+                                    quality,
+                                };
+                                shared.add_new_item(new).unwrap()
+                            })
+                            .collect::<Vec<u32>>()
+                    })
+                })
+                .collect();
+            workers
+                .into_iter()
+                .flat_map(|worker| worker.join().unwrap())
+                .collect()
+        });
+
+        assert_eq!(ids.iter().collect::<HashSet<_>>().len(), 160);
+        let mut taken = HashSet::new();
+        for id in &ids {
+            for pos in shared.positions(*id).unwrap() {
+                assert!(taken.insert(pos), "{} was handed out twice", pos);
+            }
+        }
+        // 120 normal items and 40 spanning two zones
+        assert_eq!(taken.len(), 200);
+        let placement = shared.into_inner().unwrap();
+        assert!(placement.validate().is_empty());
+    }
+}
@@ -0,0 +1,274 @@
+use crate::query::{date_from_day_number, day_number};
+use crate::route::TravelCost;
+use crate::{Layout, NewItem, Placement, Position, Quality, Strategy};
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+
+// xorshift64*, good enough to generate traffic and the same seed always gives the same run
+#[derive(Debug, Clone)]
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        // the state must never be 0
+        Rng(seed ^ 0x9E37_79B9_7F4A_7C15 | 1)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    // uniform in from..=to
+    pub fn range(&mut self, from: u32, to: u32) -> u32 {
+        if to <= from {
+            return from;
+        }
+        from + (self.next_u64() % (to - from + 1) as u64) as u32
+    }
+}
+
+// Relative weights of the qualities of arriving items
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct QualityMix {
+    pub normal: u32,
+    pub fragile: u32,
+    pub oversized: u32,
+    pub refrigerated: u32, // needs temperature zones in the layout to be placed
+    pub hazardous: u32,
+}
+
+impl Default for QualityMix {
+    fn default() -> QualityMix {
+        QualityMix {
+            normal: 60,
+            fragile: 20,
+            oversized: 20,
+            refrigerated: 0,
+            hazardous: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SimulationConfig {
+    pub seed: u64,
+    pub days: u32,
+    pub arrivals_per_day: (u32, u32), // inclusive range
+    pub mix: QualityMix,
+    pub oversized_zones: (u32, u32),
+    pub lifetime_days: (u32, u32), // how long an item stays before it is picked
+    pub hazard_classes: u32,       // hazardous items get a class in 1..=hazard_classes
+    pub start_date: [u32; 3],
+    pub dock: Position,
+    pub travel_cost: TravelCost,
+}
+
+impl Default for SimulationConfig {
+    fn default() -> SimulationConfig {
+        SimulationConfig {
+            seed: 42,
+            days: 60,
+            arrivals_per_day: (10, 30),
+            mix: QualityMix::default(),
+            oversized_zones: (2, 4),
+            lifetime_days: (3, 20),
+            hazard_classes: 3,
+            start_date: [1, 1, 2025],
+            dock: Position::new(0, 0, 0),
+            travel_cost: TravelCost::default(),
+        }
+    }
+}
+
+// State of the warehouse at the end of one simulated day
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DaySample {
+    pub day: u32,
+    pub arrivals: u32,
+    pub failures: u32, // arrivals that could not be placed
+    pub departures: u32,
+    pub utilisation: f64,
+    pub fragmentation: f64,
+    pub largest_free_run: u32,
+    pub travel: u64, // length of the pick route for the day's departures
+}
+
+#[derive(Debug, Clone)]
+pub struct SimulationReport {
+    pub strategy: String,
+    pub samples: Vec<DaySample>,
+}
+
+impl SimulationReport {
+    pub fn failure_rate(&self) -> f64 {
+        let arrivals: u32 = self.samples.iter().map(|s| s.arrivals).sum();
+        let failures: u32 = self.samples.iter().map(|s| s.failures).sum();
+        if arrivals == 0 {
+            0.0
+        } else {
+            failures as f64 / arrivals as f64
+        }
+    }
+
+    pub fn mean_utilisation(&self) -> f64 {
+        mean(self.samples.iter().map(|s| s.utilisation))
+    }
+
+    pub fn mean_fragmentation(&self) -> f64 {
+        mean(self.samples.iter().map(|s| s.fragmentation))
+    }
+
+    pub fn total_travel(&self) -> u64 {
+        self.samples.iter().map(|s| s.travel).sum()
+    }
+}
+
+fn mean(values: impl Iterator<Item = f64>) -> f64 {
+    let (sum, count) = values.fold((0.0, 0), |(sum, count), v| (sum + v, count + 1));
+    if count == 0 { 0.0 } else { sum / count as f64 }
+}
+
+impl Display for SimulationReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Strategy: {}", self.strategy)?;
+        writeln!(
+            f,
+            "{:>5} {:>8} {:>8} {:>10} {:>7} {:>7} {:>8} {:>8}",
+            "day", "arrived", "failed", "departed", "used%", "frag%", "maxrun", "travel"
+        )?;
+        // keep long runs readable, the last day is always shown
+        let step = self.samples.len().div_ceil(30).max(1);
+        for (index, s) in self.samples.iter().enumerate() {
+            if index % step != 0 && index + 1 != self.samples.len() {
+                continue;
+            }
+            writeln!(
+                f,
+                "{:>5} {:>8} {:>8} {:>10} {:>7.1} {:>7.1} {:>8} {:>8}",
+                s.day,
+                s.arrivals,
+                s.failures,
+                s.departures,
+                s.utilisation * 100.0,
+                s.fragmentation * 100.0,
+                s.largest_free_run,
+                s.travel
+            )?;
+        }
+        writeln!(f, "Mean utilisation: {:.1}%", self.mean_utilisation() * 100.0)?;
+        writeln!(
+            f,
+            "Allocation failure rate: {:.1}%",
+            self.failure_rate() * 100.0
+        )?;
+        writeln!(
+            f,
+            "Mean fragmentation: {:.1}%",
+            self.mean_fragmentation() * 100.0
+        )?;
+        write!(f, "Total travel distance: {}", self.total_travel())
+    }
+}
+
+fn random_quality(rng: &mut Rng, config: &SimulationConfig, expires_on: i64) -> Quality {
+    let mix = config.mix;
+    let total = mix.normal + mix.fragile + mix.oversized + mix.refrigerated + mix.hazardous;
+    let mut roll = rng.range(1, total.max(1));
+    for (weight, kind) in [
+        (mix.fragile, 0),
+        (mix.oversized, 1),
+        (mix.refrigerated, 2),
+        (mix.hazardous, 3),
+    ] {
+        if roll <= weight {
+            return match kind {
+                0 => Quality::Fragile {
+                    expiration_date: date_from_day_number(expires_on),
+                    row: u32::MAX, // no row restriction, only the date matters here
+                },
+                1 => Quality::Oversized {
+                    continuous_zones: rng.range(config.oversized_zones.0, config.oversized_zones.1),
+                },
+                2 => Quality::Refrigerated {
+                    min_temp: -30,
+                    max_temp: 10,
+                },
+                _ => Quality::Hazardous {
+                    class: rng.range(1, config.hazard_classes.max(1)),
+                },
+            };
+        }
+        roll -= weight;
+    }
+    Quality::Normal
+}
+
+// Runs `config.days` days of random traffic through an empty warehouse with the given layout
+// and strategy. Every day the items due to leave are picked (one route from the dock), then
+// the new arrivals are allocated.
+pub fn simulate(
+    config: &SimulationConfig,
+    layout: Layout,
+    strategy: Box<dyn Strategy>,
+) -> SimulationReport {
+    let strategy_name = format!("{:?}", strategy);
+    let mut placement = Placement::with_layout(layout);
+    placement.configure_strategy(strategy);
+
+    let mut rng = Rng::new(config.seed);
+    let start = day_number(config.start_date);
+    let mut departures: BTreeMap<u32, Vec<u32>> = BTreeMap::new();
+    let mut samples = Vec::new();
+
+    for day in 0..config.days {
+        let leaving: Vec<u32> = departures
+            .remove(&day)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|id| placement.storage.items.contains_key(id))
+            .collect();
+        let travel = placement
+            .pick_route(&leaving, config.dock, &config.travel_cost)
+            .map_or(0, |route| route.total);
+        for id in &leaving {
+            let _ = placement.remove_item(*id);
+        }
+
+        let arrivals = rng.range(config.arrivals_per_day.0, config.arrivals_per_day.1);
+        let mut failures = 0;
+        for arrival in 0..arrivals {
+            let lifetime = rng.range(config.lifetime_days.0, config.lifetime_days.1).max(1);
+            let quality = random_quality(&mut rng, config, start + (day + lifetime) as i64);
+            let item = NewItem {
+                id: None,
+                name: format!("sim-{}-{}", day, arrival),
+                quantity: rng.range(1, 10),
+                quality,
+            };
+            match placement.add_new_item(item) {
+                Ok(id) => departures.entry(day + lifetime).or_default().push(id),
+                Err(_) => failures += 1,
+            }
+        }
+
+        let stats = placement.stats();
+        samples.push(DaySample {
+            day,
+            arrivals,
+            failures,
+            departures: leaving.len() as u32,
+            utilisation: stats.overall.ratio(),
+            fragmentation: stats.fragmentation(),
+            largest_free_run: placement.storage.largest_free_run(),
+            travel,
+        });
+    }
+
+    SimulationReport {
+        strategy: strategy_name,
+        samples,
+    }
+}
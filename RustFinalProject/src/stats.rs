@@ -0,0 +1,153 @@
+use crate::Placement;
+use crate::query::QualityKind;
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct Utilisation {
+    pub used: u32,
+    pub total: u32,
+}
+
+impl Utilisation {
+    // 0.0 ..= 1.0, an empty area counts as unused
+    pub fn ratio(&self) -> f64 {
+        if self.total == 0 {
+            0.0
+        } else {
+            self.used as f64 / self.total as f64
+        }
+    }
+
+    pub fn free(&self) -> u32 {
+        self.total - self.used
+    }
+}
+
+impl Display for Utilisation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}/{} ({:.1}%)",
+            self.used,
+            self.total,
+            self.ratio() * 100.0
+        )
+    }
+}
+
+// Snapshot of how full the warehouse is, see Placement::stats
+#[derive(Debug, Clone)]
+pub struct Stats {
+    pub overall: Utilisation,
+    pub per_row: BTreeMap<u32, Utilisation>,
+    pub per_shelf: BTreeMap<(u32, u32), Utilisation>, // (row, shelf)
+    pub by_quality: BTreeMap<QualityKind, u32>,       // number of stored items
+    // longest run of consecutive free zones on each (row, shelf), i.e. the biggest
+    // Oversized item that could still go there
+    pub longest_free_run: BTreeMap<(u32, u32), u32>,
+}
+
+impl Stats {
+    // biggest contiguous free space anywhere in the warehouse
+    pub fn largest_free_run(&self) -> u32 {
+        self.longest_free_run.values().copied().max().unwrap_or(0)
+    }
+
+    // how scattered the free space is: 0 when every shelf has its free zones in one run,
+    // approaching 1 when they are chopped into single gaps (averaged over shelves with free space)
+    pub fn fragmentation(&self) -> f64 {
+        let scores: Vec<f64> = self
+            .longest_free_run
+            .iter()
+            .filter_map(|(key, run)| {
+                let free = self.per_shelf.get(key)?.free();
+                (free > 0).then(|| 1.0 - *run as f64 / free as f64)
+            })
+            .collect();
+        if scores.is_empty() {
+            0.0
+        } else {
+            scores.iter().sum::<f64>() / scores.len() as f64
+        }
+    }
+}
+
+impl Display for Stats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Positions used: {}", self.overall)?;
+        writeln!(f, "Positions free: {}", self.overall.free())?;
+
+        writeln!(f, "Items by quality:")?;
+        for kind in [
+            QualityKind::Fragile,
+            QualityKind::Oversized,
+            QualityKind::Normal,
+            QualityKind::Refrigerated,
+            QualityKind::Hazardous,
+        ] {
+            let count = self.by_quality.get(&kind).copied().unwrap_or(0);
+            writeln!(f, "  {}: {}", kind, count)?;
+        }
+
+        writeln!(f, "Utilisation per row:")?;
+        for (row, utilisation) in &self.per_row {
+            writeln!(f, "  row {}: {}", row, utilisation)?;
+        }
+
+        // one line per row, one column per shelf: used% / longest free run
+        writeln!(f, "Per shelf (used% / longest free run):")?;
+        for row in self.per_row.keys() {
+            write!(f, "  row {}:", row)?;
+            for ((_, shelf), utilisation) in self.per_shelf.range((*row, 0)..=(*row, u32::MAX)) {
+                let run = self.longest_free_run.get(&(*row, *shelf)).unwrap_or(&0);
+                write!(f, " {:>3.0}%/{}", utilisation.ratio() * 100.0, run)?;
+            }
+            writeln!(f)?;
+        }
+
+        writeln!(f, "Largest free run: {} zones", self.largest_free_run())?;
+        write!(f, "Fragmentation: {:.1}%", self.fragmentation() * 100.0)
+    }
+}
+
+impl Placement {
+    pub fn stats(&self) -> Stats {
+        let mut overall = Utilisation::default();
+        let mut per_row: BTreeMap<u32, Utilisation> = BTreeMap::new();
+        let mut per_shelf: BTreeMap<(u32, u32), Utilisation> = BTreeMap::new();
+
+        for (pos, owner) in self.storage.cells() {
+            let used = owner.is_some() as u32;
+            for utilisation in [
+                &mut overall,
+                per_row.entry(pos.row).or_default(),
+                per_shelf.entry((pos.row, pos.shelf)).or_default(),
+            ] {
+                utilisation.used += used;
+                utilisation.total += 1;
+            }
+        }
+
+        // shelves without any free run stay at 0
+        let mut longest_free_run: BTreeMap<(u32, u32), u32> =
+            per_shelf.keys().map(|shelf| (*shelf, 0)).collect();
+        for (pos, length) in self.storage.free_runs() {
+            let longest = longest_free_run.entry((pos.row, pos.shelf)).or_insert(0);
+            *longest = (*longest).max(length);
+        }
+
+        let mut by_quality = BTreeMap::new();
+        for item in self.storage.items.values() {
+            *by_quality.entry(QualityKind::of(&item.quality)).or_insert(0) += 1;
+        }
+
+        Stats {
+            overall,
+            per_row,
+            per_shelf,
+            by_quality,
+            longest_free_run,
+        }
+    }
+}
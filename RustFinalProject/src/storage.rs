@@ -0,0 +1,479 @@
+use crate::{Item, Position, Quality};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+// What is stored where: one dense cell per (row, shelf, zone) holding the id of the item
+// that covers it, and the items themselves by id. Oversized items own every cell of their span.
+// Free cells are also indexed as maximal runs of free zones per (row, shelf): in layout
+// order, by length, and in a max tree over the cell a run starts at, so both the first and
+// the best fitting span are found without scanning the grid.
+// Cells held for an inbound delivery are left out of the free runs as well, so no strategy
+// hands them to anything else.
+#[derive(Debug, Clone)]
+pub struct Storage {
+    rows: u32,
+    shelves: u32,
+    zones: u32,
+    cells: Vec<Option<u32>>, // row-major: row, then shelf, then zone
+    pub items: HashMap<u32, Item>,
+    reserved: HashMap<Position, String>, // cell -> ASN of the delivery it is held for
+    reserved_hazards: HashMap<Position, u32>, // held cell -> class of its hazardous delivery
+    runs: BTreeMap<(u32, u32, u32), u32>,   // (row, shelf, first zone) -> length
+    by_length: BTreeSet<(u32, u32, u32, u32)>, // (length, row, shelf, first zone)
+    run_starts: MaxTree,                       // cell index -> length of the run starting there
+}
+
+// Segment tree of the largest value below each node, over a fixed number of slots.
+// Finds the leftmost slot holding at least some value in O(log n).
+#[derive(Debug, Clone)]
+struct MaxTree {
+    size: usize,    // slots rounded up to a power of two, the leaves start here
+    tree: Vec<u32>, // tree[1] is the root, tree[i] covers tree[2i] and tree[2i + 1]
+}
+
+impl MaxTree {
+    fn new(slots: usize) -> MaxTree {
+        let size = slots.next_power_of_two();
+        MaxTree {
+            size,
+            tree: vec![0; 2 * size],
+        }
+    }
+
+    fn get(&self, slot: usize) -> u32 {
+        self.tree[self.size + slot]
+    }
+
+    fn set(&mut self, slot: usize, value: u32) {
+        let mut node = self.size + slot;
+        self.tree[node] = value;
+        while node > 1 {
+            node /= 2;
+            self.tree[node] = self.tree[2 * node].max(self.tree[2 * node + 1]);
+        }
+    }
+
+    // leftmost slot whose value is at least `min`
+    fn first_at_least(&self, min: u32) -> Option<usize> {
+        if self.tree[1] < min {
+            return None;
+        }
+        let mut node = 1;
+        while node < self.size {
+            node = match self.tree[2 * node] >= min {
+                true => 2 * node,
+                false => 2 * node + 1,
+            };
+        }
+        Some(node - self.size)
+    }
+}
+
+impl Storage {
+    pub fn new(rows: u32, shelves: u32, zones: u32) -> Storage {
+        let cells = (rows as usize)
+            .checked_mul(shelves as usize)
+            .and_then(|cells| cells.checked_mul(zones as usize))
+            .expect("the layout has more cells than memory can address");
+        let mut storage = Storage {
+            rows,
+            shelves,
+            zones,
+            cells: vec![None; cells],
+            items: HashMap::new(),
+            reserved: HashMap::new(),
+            reserved_hazards: HashMap::new(),
+            runs: BTreeMap::new(),
+            by_length: BTreeSet::new(),
+            run_starts: MaxTree::new(cells),
+        };
+        // every shelf starts as one free run
+        for row in 0..rows {
+            for shelf in 0..shelves {
+                storage.insert_run(row, shelf, 0, zones);
+            }
+        }
+        storage
+    }
+
+    fn insert_run(&mut self, row: u32, shelf: u32, start: u32, length: u32) {
+        if length > 0 {
+            self.runs.insert((row, shelf, start), length);
+            self.by_length.insert((length, row, shelf, start));
+            self.run_starts.set(self.slot(row, shelf, start), length);
+        }
+    }
+
+    fn remove_run(&mut self, row: u32, shelf: u32, start: u32) -> Option<u32> {
+        let length = self.runs.remove(&(row, shelf, start))?;
+        self.by_length.remove(&(length, row, shelf, start));
+        self.run_starts.set(self.slot(row, shelf, start), 0);
+        Some(length)
+    }
+
+    // index of a cell known to be inside the grid
+    fn slot(&self, row: u32, shelf: u32, zone: u32) -> usize {
+        ((row * self.shelves + shelf) * self.zones + zone) as usize
+    }
+
+    fn position_of(&self, index: usize) -> Position {
+        let index = index as u32;
+        let zone = index % self.zones;
+        let shelf = index / self.zones % self.shelves;
+        let row = index / (self.zones * self.shelves);
+        Position::new(row, shelf, zone)
+    }
+
+    // (first zone, length) of the free run that contains the cell
+    fn run_at(&self, pos: &Position) -> Option<(u32, u32)> {
+        let (&(row, shelf, start), &length) = self
+            .runs
+            .range(..=(pos.row, pos.shelf, pos.zone))
+            .next_back()?;
+        (row == pos.row && shelf == pos.shelf && pos.zone < start + length).then_some((start, length))
+    }
+
+    // None for positions outside the grid
+    fn index(&self, pos: &Position) -> Option<usize> {
+        (pos.row < self.rows && pos.shelf < self.shelves && pos.zone < self.zones)
+            .then(|| self.slot(pos.row, pos.shelf, pos.zone))
+    }
+
+    pub fn contains(&self, pos: &Position) -> bool {
+        self.index(pos).is_some()
+    }
+
+    // id of the item covering the cell
+    pub fn owner(&self, pos: &Position) -> Option<u32> {
+        self.index(pos).and_then(|index| self.cells[index])
+    }
+
+    pub fn is_occupied(&self, pos: &Position) -> bool {
+        self.owner(pos).is_some()
+    }
+
+    // inside the grid, empty and not held for a delivery
+    pub fn is_free(&self, pos: &Position) -> bool {
+        self.index(pos)
+            .is_some_and(|index| self.cells[index].is_none() && !self.reserved.contains_key(pos))
+    }
+
+    // ASN of the delivery the cell is held for
+    pub fn reservation(&self, pos: &Position) -> Option<&str> {
+        self.reserved.get(pos).map(|asn| asn.as_str())
+    }
+
+    // every held cell with its ASN, in no particular order
+    pub fn reserved_cells(&self) -> impl Iterator<Item = (&Position, &String)> + '_ {
+        self.reserved.iter()
+    }
+
+    // `span` free cells on the same shelf, starting at `pos`; an empty span never fits
+    pub fn is_run_free(&self, pos: &Position, span: u32) -> bool {
+        span > 0
+            && self
+                .run_at(pos)
+                .is_some_and(|(start, length)| pos.zone + span <= start + length)
+    }
+
+    // start of the first `span` free zones in layout order (row, shelf, zone)
+    pub fn first_free_run(&self, span: u32) -> Option<Position> {
+        if span == 0 {
+            return None;
+        }
+        // cell indices follow layout order, so the leftmost long enough run is the first
+        self.run_starts
+            .first_at_least(span)
+            .map(|index| self.position_of(index))
+    }
+
+    // start of the shortest run that still holds `span` zones, the earliest one on a tie
+    pub fn best_free_run(&self, span: u32) -> Option<Position> {
+        if span == 0 {
+            return None;
+        }
+        self.by_length
+            .range((span, 0, 0, 0)..)
+            .next()
+            .map(|&(_, row, shelf, start)| Position::new(row, shelf, start))
+    }
+
+    // the widest oversized item that still fits anywhere
+    pub fn largest_free_run(&self) -> u32 {
+        self.by_length.last().map_or(0, |(length, ..)| *length)
+    }
+
+    // false if the run index no longer describes the free cells
+    pub fn runs_match_cells(&self) -> bool {
+        let mut expected = BTreeMap::new();
+        let mut current: Option<((u32, u32, u32), u32)> = None;
+        for (pos, owner) in self.cells() {
+            let free = owner.is_none() && !self.reserved.contains_key(&pos);
+            if let Some((start, length)) = current
+                && (!free || pos.zone == 0)
+            {
+                expected.insert(start, length);
+                current = None;
+            }
+            if free {
+                match &mut current {
+                    Some((_, length)) => *length += 1,
+                    None => current = Some(((pos.row, pos.shelf, pos.zone), 1)),
+                }
+            }
+        }
+        if let Some((start, length)) = current {
+            expected.insert(start, length);
+        }
+        let lengths_match = self.by_length.len() == self.runs.len()
+            && self.runs.iter().all(|(&(row, shelf, start), &length)| {
+                self.by_length.contains(&(length, row, shelf, start))
+            });
+        let starts_match = (0..self.cells.len()).all(|index| {
+            let (row, shelf, zone) = self.position_of(index).as_tuple();
+            self.run_starts.get(index) == self.runs.get(&(row, shelf, zone)).copied().unwrap_or(0)
+        });
+        expected == self.runs && lengths_match && starts_match
+    }
+
+    // every free run as (first cell, length), in layout order
+    pub fn free_runs(&self) -> impl Iterator<Item = (Position, u32)> + '_ {
+        self.runs
+            .iter()
+            .map(|(&(row, shelf, start), &length)| (Position::new(row, shelf, start), length))
+    }
+
+    // positions outside the grid are ignored
+    pub fn set_owner(&mut self, pos: &Position, owner: Option<u32>) {
+        let Some(index) = self.index(pos) else {
+            return;
+        };
+        let was_free = self.is_free(pos);
+        self.cells[index] = owner;
+        self.update_runs(pos, was_free);
+    }
+
+    // holds the cell for delivery `asn`, None releases it; positions outside the grid are ignored
+    pub fn set_reserved(&mut self, pos: &Position, asn: Option<String>) {
+        if !self.contains(pos) {
+            return;
+        }
+        let was_free = self.is_free(pos);
+        match asn {
+            Some(asn) => self.reserved.insert(*pos, asn),
+            None => {
+                self.reserved_hazards.remove(pos);
+                self.reserved.remove(pos)
+            }
+        };
+        self.update_runs(pos, was_free);
+    }
+
+    // the held cell waits for hazardous goods of `class`, so segregation keeps its distance
+    // before they arrive; released together with the cell
+    pub fn set_reserved_hazard(&mut self, pos: &Position, class: u32) {
+        if self.reserved.contains_key(pos) {
+            self.reserved_hazards.insert(*pos, class);
+        }
+    }
+
+    fn update_runs(&mut self, pos: &Position, was_free: bool) {
+        let (row, shelf, zone) = pos.as_tuple();
+        match (was_free, self.is_free(pos)) {
+            // split the run around the cell
+            (true, false) => {
+                if let Some((start, length)) = self.run_at(pos) {
+                    self.remove_run(row, shelf, start);
+                    self.insert_run(row, shelf, start, zone - start);
+                    self.insert_run(row, shelf, zone + 1, start + length - zone - 1);
+                }
+            }
+            // join the cell with the runs right before and after it
+            (false, true) => {
+                let mut start = zone;
+                let mut length = 1;
+                if zone > 0
+                    && let Some((before, before_length)) =
+                        self.run_at(&Position::new(row, shelf, zone - 1))
+                {
+                    self.remove_run(row, shelf, before);
+                    start = before;
+                    length += before_length;
+                }
+                if let Some(after_length) = self.remove_run(row, shelf, zone + 1) {
+                    length += after_length;
+                }
+                self.insert_run(row, shelf, start, length);
+            }
+            _ => {}
+        }
+    }
+
+    // every cell with the item covering it, row by row, shelf by shelf
+    pub fn cells(&self) -> impl Iterator<Item = (Position, Option<u32>)> + '_ {
+        self.cells
+            .iter()
+            .enumerate()
+            .map(|(index, owner)| (self.position_of(index), *owner))
+    }
+
+    // (position, id, class) of every hazardous item
+    pub fn hazards(&self) -> Vec<(Position, u32, u32)> {
+        self.cells()
+            .filter_map(|(pos, owner)| match self.items.get(&owner?)?.quality {
+                Quality::Hazardous { class } => Some((pos, owner?, class)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    // (position, ASN, class) of every cell held for a hazardous delivery
+    pub fn reserved_hazards(&self) -> Vec<(Position, String, u32)> {
+        let mut hazards: Vec<(Position, String, u32)> = self
+            .reserved_hazards
+            .iter()
+            .filter_map(|(pos, class)| Some((*pos, self.reserved.get(pos)?.clone(), *class)))
+            .collect();
+        hazards.sort_by_key(|(pos, _, _)| pos.as_tuple());
+        hazards
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn runs(storage: &Storage) -> Vec<((u32, u32, u32), u32)> {
+        storage
+            .free_runs()
+            .map(|(pos, length)| (pos.as_tuple(), length))
+            .collect()
+    }
+
+    #[test]
+    fn new_storage_is_one_run_per_shelf() {
+        let storage = Storage::new(2, 2, 5);
+        assert_eq!(
+            runs(&storage),
+            vec![((0, 0, 0), 5), ((0, 1, 0), 5), ((1, 0, 0), 5), ((1, 1, 0), 5)]
+        );
+        assert_eq!(storage.largest_free_run(), 5);
+        assert!(storage.runs_match_cells());
+    }
+
+    #[test]
+    fn occupying_a_cell_splits_its_run() {
+        let mut storage = Storage::new(1, 1, 6);
+        storage.set_owner(&Position::new(0, 0, 2), Some(1));
+        assert_eq!(runs(&storage), vec![((0, 0, 0), 2), ((0, 0, 3), 3)]);
+        assert!(storage.runs_match_cells());
+
+        // at the edges only one side is left
+        storage.set_owner(&Position::new(0, 0, 0), Some(2));
+        storage.set_owner(&Position::new(0, 0, 5), Some(3));
+        assert_eq!(runs(&storage), vec![((0, 0, 1), 1), ((0, 0, 3), 2)]);
+        assert!(storage.runs_match_cells());
+    }
+
+    #[test]
+    fn freeing_a_cell_merges_it_with_its_neighbours() {
+        let mut storage = Storage::new(1, 1, 5);
+        for zone in [1, 2, 3] {
+            storage.set_owner(&Position::new(0, 0, zone), Some(zone));
+        }
+        assert_eq!(runs(&storage), vec![((0, 0, 0), 1), ((0, 0, 4), 1)]);
+
+        storage.set_owner(&Position::new(0, 0, 2), None);
+        assert_eq!(runs(&storage), vec![((0, 0, 0), 1), ((0, 0, 2), 1), ((0, 0, 4), 1)]);
+        storage.set_owner(&Position::new(0, 0, 1), None);
+        assert_eq!(runs(&storage), vec![((0, 0, 0), 3), ((0, 0, 4), 1)]);
+        storage.set_owner(&Position::new(0, 0, 3), None);
+        assert_eq!(runs(&storage), vec![((0, 0, 0), 5)]);
+        assert!(storage.runs_match_cells());
+    }
+
+    #[test]
+    fn changing_the_owner_of_a_taken_cell_keeps_the_runs() {
+        let mut storage = Storage::new(1, 1, 3);
+        storage.set_owner(&Position::new(0, 0, 1), Some(1));
+        storage.set_owner(&Position::new(0, 0, 1), Some(2));
+        assert_eq!(storage.owner(&Position::new(0, 0, 1)), Some(2));
+        assert_eq!(runs(&storage), vec![((0, 0, 0), 1), ((0, 0, 2), 1)]);
+        assert!(storage.runs_match_cells());
+    }
+
+    #[test]
+    fn reserved_cells_are_not_free() {
+        let mut storage = Storage::new(1, 1, 4);
+        let pos = Position::new(0, 0, 1);
+        storage.set_reserved(&pos, Some("ASN-1".to_string()));
+        assert!(!storage.is_free(&pos));
+        assert!(!storage.is_occupied(&pos));
+        assert_eq!(storage.reservation(&pos), Some("ASN-1"));
+        assert_eq!(runs(&storage), vec![((0, 0, 0), 1), ((0, 0, 2), 2)]);
+        assert!(storage.runs_match_cells());
+
+        storage.set_reserved(&pos, None);
+        assert_eq!(runs(&storage), vec![((0, 0, 0), 4)]);
+        assert!(storage.runs_match_cells());
+    }
+
+    #[test]
+    fn first_and_best_free_run() {
+        let mut storage = Storage::new(1, 3, 6);
+        // shelf 0: run of 2, shelf 1: run of 4, shelf 2: run of 3
+        for zone in 2..6 {
+            storage.set_owner(&Position::new(0, 0, zone), Some(1));
+        }
+        for zone in 0..2 {
+            storage.set_owner(&Position::new(0, 1, zone), Some(2));
+        }
+        for zone in 3..6 {
+            storage.set_owner(&Position::new(0, 2, zone), Some(3));
+        }
+
+        assert_eq!(storage.first_free_run(1), Some(Position::new(0, 0, 0)));
+        assert_eq!(storage.first_free_run(3), Some(Position::new(0, 1, 2)));
+        assert_eq!(storage.first_free_run(5), None);
+        assert_eq!(storage.first_free_run(0), None);
+
+        assert_eq!(storage.best_free_run(3), Some(Position::new(0, 2, 0)));
+        assert_eq!(storage.best_free_run(4), Some(Position::new(0, 1, 2)));
+        assert_eq!(storage.best_free_run(0), None);
+
+        assert!(storage.is_run_free(&Position::new(0, 1, 2), 4));
+        assert!(!storage.is_run_free(&Position::new(0, 1, 3), 4));
+        assert!(!storage.is_run_free(&Position::new(0, 1, 3), 0));
+    }
+
+    #[test]
+    fn runs_match_cells_catches_a_stale_index() {
+        let mut storage = Storage::new(1, 1, 4);
+        storage.cells[1] = Some(1); // behind set_owner's back
+        assert!(!storage.runs_match_cells());
+    }
+
+    #[test]
+    fn first_free_run_agrees_with_a_scan() {
+        let mut storage = Storage::new(3, 4, 7);
+        let mut state: u64 = 7;
+        for step in 0..500 {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            let index = (state % storage.cells.len() as u64) as usize;
+            let pos = storage.position_of(index);
+            let owner = (step % 3 != 0).then_some(step);
+            storage.set_owner(&pos, owner);
+
+            assert!(storage.runs_match_cells());
+            for span in 1..=7 {
+                let scanned = storage
+                    .runs
+                    .iter()
+                    .find(|(_, length)| **length >= span)
+                    .map(|(&(row, shelf, start), _)| Position::new(row, shelf, start));
+                assert_eq!(storage.first_free_run(span), scanned);
+            }
+        }
+    }
+}
@@ -0,0 +1,513 @@
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout as Split, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Clear, List, ListItem, ListState, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+use warehouse::query::{QualityKind, parse_date};
+use warehouse::{Item, MyError, NewItem, Placement, Position, Quality};
+
+const KINDS: [QualityKind; 5] = [
+    QualityKind::Normal,
+    QualityKind::Fragile,
+    QualityKind::Oversized,
+    QualityKind::Refrigerated,
+    QualityKind::Hazardous,
+];
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum Pane {
+    Grid,
+    Items,
+}
+
+// The add item popup. Quality is picked with left/right, the details field is read
+// according to it, see `detail_hint`.
+#[derive(Debug, Default)]
+struct AddForm {
+    id: String,
+    name: String,
+    quantity: String,
+    kind: usize, // index into KINDS
+    detail: String,
+    field: usize, // 0 id, 1 name, 2 quantity, 3 quality, 4 details
+    error: Option<String>,
+}
+
+impl AddForm {
+    const FIELDS: usize = 5;
+    const LABELS: [&'static str; 5] = ["Id", "Name", "Quantity", "Quality", "Details"];
+
+    fn detail_hint(&self) -> &'static str {
+        match KINDS[self.kind] {
+            QualityKind::Normal => "nothing",
+            QualityKind::Fragile => "dd-mm-yyyy row, e.g. 01-02-2025 3",
+            QualityKind::Oversized => "number of zones",
+            QualityKind::Refrigerated => "min..max °C, e.g. -20..4",
+            QualityKind::Hazardous => "hazard class",
+        }
+    }
+
+    fn text_field(&mut self) -> Option<&mut String> {
+        match self.field {
+            0 => Some(&mut self.id),
+            1 => Some(&mut self.name),
+            2 => Some(&mut self.quantity),
+            4 => Some(&mut self.detail),
+            _ => None,
+        }
+    }
+
+    fn quality(&self) -> Result<Quality, MyError> {
+        let number = |text: &str| text.trim().parse::<u32>().map_err(MyError::ParseIntError);
+        let detail = self.detail.trim();
+        match KINDS[self.kind] {
+            QualityKind::Normal => Ok(Quality::Normal),
+            QualityKind::Fragile => {
+                let (date, row) = detail
+                    .split_once(' ')
+                    .ok_or_else(|| MyError::WrongOption("expected a date and a row".to_string()))?;
+                Ok(Quality::Fragile {
+                    expiration_date: parse_date(date)?,
+                    row: number(row)?,
+                })
+            }
+            QualityKind::Oversized => Ok(Quality::Oversized {
+                continuous_zones: number(detail)?,
+            }),
+            QualityKind::Refrigerated => {
+                let (min, max) = detail
+                    .split_once("..")
+                    .ok_or_else(|| MyError::WrongOption("expected min..max".to_string()))?;
+                let temperature = |text: &str| {
+                    text.trim()
+                        .parse::<i32>()
+                        .map_err(|_| MyError::WrongOption(format!("{} is not a temperature", text)))
+                };
+                Ok(Quality::Refrigerated {
+                    min_temp: temperature(min)?,
+                    max_temp: temperature(max)?,
+                })
+            }
+            QualityKind::Hazardous => Ok(Quality::Hazardous {
+                class: number(detail)?,
+            }),
+        }
+    }
+
+    // Checks every field, an empty id means the placement picks one.
+    // A bad field comes back with its index so the form can jump to it.
+    fn item(&self) -> Result<NewItem, (usize, MyError)> {
+        let id = match self.id.trim() {
+            "" => None,
+            id => Some(id.parse::<u32>().map_err(|err| (0, MyError::ParseIntError(err)))?),
+        };
+        let name = self.name.trim().to_string();
+        if name.is_empty() {
+            return Err((1, MyError::WrongOption("the name cannot be empty".to_string())));
+        }
+        let quantity = self
+            .quantity
+            .trim()
+            .parse::<u32>()
+            .map_err(|err| (2, MyError::ParseIntError(err)))?;
+        let quality = self.quality().map_err(|err| (4, err))?;
+        Ok(NewItem {
+            id,
+            name,
+            quantity,
+            quality,
+        })
+    }
+}
+
+struct App<'a> {
+    name: &'a str,
+    placement: &'a mut Placement,
+    pane: Pane,
+    cursor: Position,
+    search: String,
+    searching: bool,
+    list: ListState,
+    form: Option<AddForm>,
+    message: String,
+    quit: bool,
+}
+
+impl App<'_> {
+    // items matching the search (name contains it, or exact id), by name
+    fn items(&self) -> Vec<&Item> {
+        let search = self.search.to_lowercase();
+        let mut items: Vec<&Item> = self
+            .placement
+            .storage()
+            .items
+            .values()
+            .filter(|item| {
+                search.is_empty()
+                    || item.name.to_lowercase().contains(&search)
+                    || item.id.to_string() == search
+            })
+            .collect();
+        items.sort_by_key(|item| (item.name.to_lowercase(), item.id));
+        items
+    }
+
+    fn selected_id(&self) -> Option<u32> {
+        let items = self.items();
+        self.list
+            .selected()
+            .and_then(|index| items.get(index))
+            .map(|item| item.id)
+    }
+
+    fn move_cursor(&mut self, rows: i64, shelves: i64, zones: i64) {
+        let layout = self.placement.layout();
+        let step = |value: u32, by: i64, size: u32| {
+            (value as i64 + by).clamp(0, size.saturating_sub(1) as i64) as u32
+        };
+        self.cursor = Position::new(
+            step(self.cursor.row, rows, layout.rows),
+            step(self.cursor.shelf, shelves, layout.shelves),
+            step(self.cursor.zone, zones, layout.zones),
+        );
+    }
+
+    fn handle_key(&mut self, code: KeyCode) {
+        if self.form.is_some() {
+            self.handle_form_key(code);
+            return;
+        }
+        if self.searching {
+            match code {
+                KeyCode::Enter | KeyCode::Esc => self.searching = false,
+                KeyCode::Backspace => {
+                    self.search.pop();
+                }
+                KeyCode::Char(c) => self.search.push(c),
+                _ => {}
+            }
+            self.list.select((!self.items().is_empty()).then_some(0));
+            return;
+        }
+
+        match (self.pane, code) {
+            (_, KeyCode::Char('q')) => self.quit = true,
+            (_, KeyCode::Tab) => {
+                self.pane = match self.pane {
+                    Pane::Grid => Pane::Items,
+                    Pane::Items => Pane::Grid,
+                }
+            }
+            (_, KeyCode::Char('/')) => {
+                self.pane = Pane::Items;
+                self.searching = true;
+            }
+            (_, KeyCode::Char('a')) => self.form = Some(AddForm::default()),
+            (_, KeyCode::PageUp) => self.move_cursor(-1, 0, 0),
+            (_, KeyCode::PageDown) => self.move_cursor(1, 0, 0),
+            (Pane::Grid, KeyCode::Left) => self.move_cursor(0, 0, -1),
+            (Pane::Grid, KeyCode::Right) => self.move_cursor(0, 0, 1),
+            (Pane::Grid, KeyCode::Up) => self.move_cursor(0, -1, 0),
+            (Pane::Grid, KeyCode::Down) => self.move_cursor(0, 1, 0),
+            (Pane::Grid, KeyCode::Char('d')) => {
+                if let Some(id) = self.placement.storage().owner(&self.cursor) {
+                    self.remove(id);
+                }
+            }
+            (Pane::Items, KeyCode::Up) => self.list.select_previous(),
+            (Pane::Items, KeyCode::Down) => self.list.select_next(),
+            // jump to the item in the grid
+            (Pane::Items, KeyCode::Enter) => {
+                if let Some(id) = self.selected_id()
+                    && let Some(pos) = self.placement.position_search(id).and_then(|p| p.first().copied())
+                {
+                    self.cursor = pos;
+                    self.pane = Pane::Grid;
+                }
+            }
+            (Pane::Items, KeyCode::Char('d')) => {
+                if let Some(id) = self.selected_id() {
+                    self.remove(id);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn remove(&mut self, id: u32) {
+        self.message = match self.placement.remove_item(id) {
+            Ok(()) => format!("Removed item {}", id),
+            Err(err) => err.to_string(),
+        };
+    }
+
+    fn handle_form_key(&mut self, code: KeyCode) {
+        let Some(form) = self.form.as_mut() else {
+            return;
+        };
+        match code {
+            KeyCode::Esc => self.form = None,
+            KeyCode::Tab | KeyCode::Down => form.field = (form.field + 1) % AddForm::FIELDS,
+            KeyCode::BackTab | KeyCode::Up => {
+                form.field = (form.field + AddForm::FIELDS - 1) % AddForm::FIELDS
+            }
+            KeyCode::Left if form.field == 3 => form.kind = (form.kind + KINDS.len() - 1) % KINDS.len(),
+            KeyCode::Right if form.field == 3 => form.kind = (form.kind + 1) % KINDS.len(),
+            KeyCode::Backspace => {
+                if let Some(text) = form.text_field() {
+                    text.pop();
+                }
+            }
+            KeyCode::Char(c) => {
+                if let Some(text) = form.text_field() {
+                    text.push(c);
+                }
+            }
+            KeyCode::Enter => {
+                let item = match form.item() {
+                    Ok(item) => item,
+                    Err((field, err)) => {
+                        form.error = Some(format!("{}: {}", AddForm::LABELS[field], err));
+                        form.field = field;
+                        return;
+                    }
+                };
+                match self.placement.add_new_item(item) {
+                    Ok(id) => {
+                        if let Some(pos) = self.placement.position_search(id).and_then(|p| p.first().copied()) {
+                            self.cursor = pos;
+                        }
+                        self.message = format!("Added item {}", id);
+                        self.form = None;
+                    }
+                    Err(err) => form.error = Some(err.to_string()),
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [title, body, status] = Split::vertical([
+            Constraint::Length(1),
+            Constraint::Min(0),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        let [left, right] =
+            Split::horizontal([Constraint::Percentage(60), Constraint::Percentage(40)]).areas(body);
+        let [grid, cell] = Split::vertical([Constraint::Min(0), Constraint::Length(4)]).areas(left);
+        let [items, stats] = Split::vertical([Constraint::Min(0), Constraint::Length(10)]).areas(right);
+
+        frame.render_widget(
+            Paragraph::new(format!(
+                "Warehouse {} | arrows move, PgUp/PgDn row, Tab pane, / search, a add, d delete, q quit",
+                self.name
+            ))
+            .style(Style::new().add_modifier(Modifier::REVERSED)),
+            title,
+        );
+        self.draw_grid(frame, grid);
+        self.draw_cell(frame, cell);
+        self.draw_items(frame, items);
+        self.draw_stats(frame, stats);
+        frame.render_widget(Paragraph::new(self.message.as_str()), status);
+        if self.form.is_some() {
+            self.draw_form(frame);
+        }
+    }
+
+    fn pane_block(&self, title: String, pane: Pane) -> Block<'static> {
+        let block = Block::bordered().title(title);
+        if self.pane == pane {
+            block.border_style(Style::new().fg(Color::Yellow))
+        } else {
+            block
+        }
+    }
+
+    fn draw_grid(&self, frame: &mut Frame, area: Rect) {
+        let layout = self.placement.layout();
+        let storage = self.placement.storage();
+        let row = self.cursor.row;
+        let title = match layout.row_temperatures.get(&row) {
+            Some(zone) => format!("Row {} ({})", row, zone),
+            None => format!("Row {}", row),
+        };
+
+        let mut lines = vec![Line::from(
+            std::iter::once(Span::raw("        "))
+                .chain((0..layout.zones).map(|zone| Span::raw(format!("{:>4}", zone))))
+                .collect::<Vec<_>>(),
+        )];
+        for shelf in 0..layout.shelves {
+            let mut spans = vec![Span::raw(format!("shelf {:<2}", shelf))];
+            for zone in 0..layout.zones {
+                let pos = Position::new(row, shelf, zone);
+                let (text, colour) = match storage.owner(&pos) {
+                    None if storage.reservation(&pos).is_some() => ("   r".to_string(), Color::Yellow),
+                    None => ("   .".to_string(), Color::DarkGray),
+                    Some(id) => {
+                        // oversized spans run along the zones, only their first cell shows the id
+                        let first = zone == 0
+                            || storage.owner(&Position::new(row, shelf, zone - 1)) != Some(id);
+                        let quality = storage.items.get(&id).map(|item| QualityKind::of(&item.quality));
+                        let colour = match quality {
+                            Some(QualityKind::Fragile) => Color::Magenta,
+                            Some(QualityKind::Oversized) => Color::Blue,
+                            Some(QualityKind::Refrigerated) => Color::Cyan,
+                            Some(QualityKind::Hazardous) => Color::Red,
+                            _ => Color::Green,
+                        };
+                        match first {
+                            true => (format!("{:>4}", id), colour),
+                            false => (" ===".to_string(), colour),
+                        }
+                    }
+                };
+                let mut style = Style::new().fg(colour);
+                if pos == self.cursor {
+                    style = style.add_modifier(Modifier::REVERSED);
+                }
+                spans.push(Span::styled(text, style));
+            }
+            lines.push(Line::from(spans));
+        }
+        frame.render_widget(
+            Paragraph::new(lines).block(self.pane_block(title, Pane::Grid)),
+            area,
+        );
+    }
+
+    fn draw_cell(&self, frame: &mut Frame, area: Rect) {
+        let storage = self.placement.storage();
+        let text = match storage.owner(&self.cursor).and_then(|id| storage.items.get(&id)) {
+            Some(item) => item.to_string(),
+            None => match storage.reservation(&self.cursor) {
+                Some(asn) => format!("reserved for delivery {}", asn),
+                None => "free".to_string(),
+            },
+        };
+        frame.render_widget(
+            Paragraph::new(text).block(Block::bordered().title(format!("Cell {}", self.cursor))),
+            area,
+        );
+    }
+
+    fn draw_items(&mut self, frame: &mut Frame, area: Rect) {
+        let rows: Vec<ListItem> = self
+            .items()
+            .iter()
+            .map(|item| {
+                ListItem::new(format!(
+                    "{:>4} {} x{} ({})",
+                    item.id,
+                    item.name,
+                    item.quantity,
+                    QualityKind::of(&item.quality)
+                ))
+            })
+            .collect();
+        let title = match (self.searching, self.search.is_empty()) {
+            (true, _) => format!("Items, search: {}_", self.search),
+            (false, false) => format!("Items matching \"{}\"", self.search),
+            (false, true) => "Items".to_string(),
+        };
+        let list = List::new(rows)
+            .block(self.pane_block(title, Pane::Items))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, area, &mut self.list);
+    }
+
+    fn draw_stats(&self, frame: &mut Frame, area: Rect) {
+        let stats = self.placement.stats();
+        let mut lines = vec![
+            Line::from(format!("Used: {}", stats.overall)),
+            Line::from(format!("Largest free run: {} zones", stats.largest_free_run())),
+            Line::from(format!("Fragmentation: {:.1}%", stats.fragmentation() * 100.0)),
+        ];
+        for (kind, count) in &stats.by_quality {
+            lines.push(Line::from(format!("{}: {}", kind, count)));
+        }
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::bordered().title("Stats")),
+            area,
+        );
+    }
+
+    fn draw_form(&self, frame: &mut Frame) {
+        let Some(form) = &self.form else {
+            return;
+        };
+        let area = frame.area();
+        let popup = Rect {
+            x: area.width.saturating_sub(60) / 2,
+            y: area.height.saturating_sub(11) / 2,
+            width: area.width.min(60),
+            height: area.height.min(11),
+        };
+        let id_hint = format!("(empty for {})", self.placement.next_free_id());
+        let quality = format!("< {} >", KINDS[form.kind]);
+        let fields = [
+            (form.id.as_str(), id_hint.as_str()),
+            (form.name.as_str(), ""),
+            (form.quantity.as_str(), ""),
+            (quality.as_str(), "left/right to change"),
+            (form.detail.as_str(), form.detail_hint()),
+        ];
+        let mut lines: Vec<Line> = AddForm::LABELS
+            .iter()
+            .zip(fields)
+            .enumerate()
+            .map(|(index, (label, (value, hint)))| {
+                let style = match index == form.field {
+                    true => Style::new().add_modifier(Modifier::REVERSED),
+                    false => Style::new(),
+                };
+                Line::from(vec![
+                    Span::raw(format!("{:<10}", label)),
+                    Span::styled(value.to_string(), style),
+                    Span::styled(format!("  {}", hint), Style::new().fg(Color::DarkGray)),
+                ])
+            })
+            .collect();
+        lines.push(Line::from(""));
+        lines.push(match &form.error {
+            Some(error) => Line::styled(error.clone(), Style::new().fg(Color::Red)),
+            None => Line::from("Enter to add, Esc to cancel"),
+        });
+        frame.render_widget(Clear, popup);
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::bordered().title("Add item")),
+            popup,
+        );
+    }
+}
+
+// Full-screen view of one warehouse until `q` is pressed
+pub fn run(terminal: &mut DefaultTerminal, name: &str, placement: &mut Placement) -> Result<(), MyError> {
+    let mut app = App {
+        name,
+        placement,
+        pane: Pane::Grid,
+        cursor: Position::new(0, 0, 0),
+        search: String::new(),
+        searching: false,
+        list: ListState::default().with_selected(Some(0)),
+        form: None,
+        message: String::new(),
+        quit: false,
+    };
+    while !app.quit {
+        terminal
+            .draw(|frame| app.draw(frame))
+            .map_err(MyError::IOError)?;
+        if let Event::Key(key) = event::read().map_err(MyError::IOError)?
+            && key.kind == KeyEventKind::Press
+        {
+            app.handle_key(key.code);
+        }
+    }
+    Ok(())
+}
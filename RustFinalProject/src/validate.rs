@@ -0,0 +1,322 @@
+use crate::{Placement, Position, Quality};
+use itertools::Itertools;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+
+// One way the placement's indices disagree with each other
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Violation {
+    // a cell is taken by an id that is not in the item table
+    UnknownOwner { pos: Position, id: u32 },
+    // a cell is taken by an item whose positions do not include it
+    UnlistedCell { pos: Position, id: u32 },
+    // an item lists a cell that is free or taken by something else
+    CellMismatch {
+        pos: Position,
+        id: u32,
+        found: Option<u32>,
+    },
+    // an item is in the item table but has no positions
+    Unplaced(u32),
+    // positions are kept for an id that is not in the item table
+    StalePositions(u32),
+    // the item's cells are not the contiguous span its quality asks for
+    BrokenSpan { id: u32, reason: String },
+    // a fragile item sits on or above its row limit
+    FragileRow { id: u32, row: u32, limit: u32 },
+    // a refrigerated item sits somewhere not kept at its temperature
+    WrongTemperature { id: u32, pos: Position, reason: String },
+    // two hazardous items are closer than their classes may be, `id` is the lower id
+    TooClose {
+        id: u32,
+        other: u32,
+        required: u32,
+        actual: u32,
+    },
+    // name search points at an item that does not exist (anymore) or has changed
+    StaleName(String),
+    // an item cannot be found by its name
+    MissingName(String),
+    // intake order is kept for an id that is not in the item table
+    StaleIntake(u32),
+    // a cell is held for a delivery that does not list it, or is held and taken at once
+    StaleReservation { pos: Position, asn: String },
+    // the free run index does not describe the free cells
+    RunIndex,
+}
+
+impl Display for Violation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Violation::UnknownOwner { pos, id } => {
+                write!(f, "cell {} is taken by unknown item {}", pos, id)
+            }
+            Violation::UnlistedCell { pos, id } => {
+                write!(f, "cell {} is taken by item {} but not among its positions", pos, id)
+            }
+            Violation::CellMismatch { pos, id, found } => match found {
+                Some(other) => write!(f, "item {} lists cell {} which holds item {}", id, pos, other),
+                None => write!(f, "item {} lists cell {} which is free", id, pos),
+            },
+            Violation::Unplaced(id) => write!(f, "item {} has no position", id),
+            Violation::StalePositions(id) => write!(f, "positions kept for unknown item {}", id),
+            Violation::BrokenSpan { id, reason } => write!(f, "item {}: {}", id, reason),
+            Violation::FragileRow { id, row, limit } => write!(
+                f,
+                "fragile item {} is in row {}, must stay below row {}",
+                id, row, limit
+            ),
+            Violation::WrongTemperature { id, pos, reason } => {
+                write!(f, "refrigerated item {} at {}: {}", id, pos, reason)
+            }
+            Violation::TooClose {
+                id,
+                other,
+                required,
+                actual,
+            } => write!(
+                f,
+                "hazardous items {} and {} are {} apart, must be at least {}",
+                id, other, actual, required
+            ),
+            Violation::StaleName(name) => write!(f, "name {} points at a missing or outdated item", name),
+            Violation::MissingName(name) => write!(f, "name {} cannot be searched", name),
+            Violation::StaleIntake(id) => write!(f, "intake kept for unknown item {}", id),
+            Violation::StaleReservation { pos, asn } => {
+                write!(f, "cell {} is held for delivery {} but cannot be used by it", pos, asn)
+            }
+            Violation::RunIndex => write!(f, "free run index does not match the free cells"),
+        }
+    }
+}
+
+impl Placement {
+    // Cross-checks the cell grid, the item table, positions, names and the free run index.
+    // An empty list means everything agrees.
+    pub fn validate(&self) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        let items = &self.storage.items;
+
+        // cell -> item listing it, every cell may be listed once
+        let mut listed: HashMap<Position, u32> = HashMap::new();
+        for (&id, positions) in self.position_map.iter().sorted_by_key(|(id, _)| **id) {
+            let Some(item) = items.get(&id) else {
+                violations.push(Violation::StalePositions(id));
+                continue;
+            };
+            for pos in positions {
+                let found = self.storage.owner(pos);
+                if found != Some(id) || listed.insert(*pos, id).is_some() {
+                    violations.push(Violation::CellMismatch {
+                        pos: *pos,
+                        id,
+                        found,
+                    });
+                }
+            }
+
+            let span = item.quality.span();
+            let Some(first) = positions.first() else {
+                violations.push(Violation::Unplaced(id));
+                continue;
+            };
+            let expected: Vec<Position> = (first.zone..first.zone + span)
+                .map(|zone| Position::new(first.row, first.shelf, zone))
+                .collect();
+            if *positions != expected {
+                violations.push(Violation::BrokenSpan {
+                    id,
+                    reason: format!(
+                        "expected {} contiguous zones from {}, found {} positions",
+                        span,
+                        first,
+                        positions.len()
+                    ),
+                });
+            }
+            if let Quality::Fragile { row, .. } = item.quality
+                && first.row >= row
+            {
+                violations.push(Violation::FragileRow {
+                    id,
+                    row: first.row,
+                    limit: row,
+                });
+            }
+            if let Quality::Refrigerated { min_temp, max_temp } = item.quality {
+                for pos in positions {
+                    if let Some(reason) = self.temperature_problem(min_temp, max_temp, pos) {
+                        violations.push(Violation::WrongTemperature { id, pos: *pos, reason });
+                    }
+                }
+            }
+        }
+
+        // every pair of hazardous cells once, the rules place() enforces on the way in
+        let hazards = self.storage.hazards();
+        let mut too_close = Vec::new();
+        for (index, (pos, id, class)) in hazards.iter().enumerate() {
+            for (other_pos, other, other_class) in &hazards[index + 1..] {
+                let required = self.layout.segregation.required(*class, *other_class);
+                let actual = pos.distance(other_pos);
+                if id != other && actual < required {
+                    too_close.push(Violation::TooClose {
+                        id: *id.min(other),
+                        other: *id.max(other),
+                        required,
+                        actual,
+                    });
+                }
+            }
+        }
+        too_close.dedup();
+        violations.extend(too_close);
+
+        for (pos, owner) in self.storage.cells() {
+            let Some(id) = owner else { continue };
+            if !items.contains_key(&id) {
+                violations.push(Violation::UnknownOwner { pos, id });
+            } else if listed.get(&pos) != Some(&id) {
+                violations.push(Violation::UnlistedCell { pos, id });
+            }
+        }
+
+        for id in items.keys().copied().sorted() {
+            if !self.position_map.contains_key(&id) {
+                violations.push(Violation::Unplaced(id));
+            }
+        }
+
+        for name in self.name_map.keys().sorted() {
+            let item = &self.name_map[name];
+            if item.name != *name || items.get(&item.id) != Some(item) {
+                violations.push(Violation::StaleName(name.clone()));
+            }
+        }
+        let mut missing: Vec<&String> = items
+            .values()
+            .map(|item| &item.name)
+            .filter(|name| !self.name_map.contains_key(*name))
+            .collect();
+        missing.sort();
+        missing.dedup();
+        for name in missing {
+            violations.push(Violation::MissingName(name.clone()));
+        }
+
+        let mut stale_intake: Vec<u32> = self
+            .intake
+            .keys()
+            .copied()
+            .filter(|id| !items.contains_key(id))
+            .collect();
+        stale_intake.sort();
+        violations.extend(stale_intake.into_iter().map(Violation::StaleIntake));
+
+        let mut held: Vec<(Position, String)> = self
+            .storage
+            .reserved_cells()
+            .filter(|(pos, asn)| {
+                self.storage.is_occupied(pos)
+                    || !self
+                        .inbound
+                        .get(*asn)
+                        .is_some_and(|reservation| reservation.positions.contains(pos))
+            })
+            .map(|(pos, asn)| (*pos, asn.clone()))
+            .collect();
+        for reservation in self.inbound.values() {
+            for pos in &reservation.positions {
+                if self.storage.reservation(pos) != Some(reservation.asn.as_str()) {
+                    held.push((*pos, reservation.asn.clone()));
+                }
+            }
+        }
+        held.sort_by_key(|(pos, asn)| (pos.as_tuple(), asn.clone()));
+        violations.extend(
+            held.into_iter()
+                .map(|(pos, asn)| Violation::StaleReservation { pos, asn }),
+        );
+
+        if !self.storage.runs_match_cells() {
+            violations.push(Violation::RunIndex);
+        }
+        violations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Item, Layout};
+
+    fn stocked() -> Placement {
+        let mut placement = Placement::with_layout(Layout::new(2, 1, 5));
+        for (id, quality) in [
+            (1, Quality::Normal),
+            (2, Quality::Oversized { continuous_zones: 2 }),
+            (3, Quality::Hazardous { class: 1 }),
+            (4, Quality::Hazardous { class: 2 }),
+        ] {
+            placement
+                .add_item(Item {
+                    id,
+                    name: format!("Item{}", id),
+                    quantity: 1,
+                    quality,
+                })
+                .unwrap();
+        }
+        placement
+    }
+
+    #[test]
+    fn a_placement_built_through_add_item_is_consistent() {
+        let mut placement = stocked();
+        placement.remove_item(2).unwrap();
+        assert_eq!(placement.validate(), vec![]);
+    }
+
+    #[test]
+    fn a_cell_taken_by_an_unknown_item_is_reported() {
+        let mut placement = stocked();
+        let pos = Position::new(1, 0, 4);
+        placement.storage.set_owner(&pos, Some(99));
+        assert_eq!(placement.validate(), vec![Violation::UnknownOwner { pos, id: 99 }]);
+    }
+
+    #[test]
+    fn positions_that_disagree_with_the_grid_are_reported() {
+        let mut placement = stocked();
+        // item 1 claims a free cell while the grid still has it at (0, 0, 0)
+        let (old, new) = (Position::new(0, 0, 0), Position::new(1, 0, 4));
+        placement.position_map.insert(1, vec![new]);
+        assert_eq!(
+            placement.validate(),
+            vec![
+                Violation::CellMismatch {
+                    pos: new,
+                    id: 1,
+                    found: None,
+                },
+                Violation::UnlistedCell { pos: old, id: 1 },
+            ]
+        );
+    }
+
+    #[test]
+    fn rules_tightened_after_placement_are_reported() {
+        let mut placement = stocked();
+        // items 3 and 4 sit next to each other
+        placement.set_segregation(1, 2, 3);
+        assert_eq!(
+            placement.validate(),
+            vec![Violation::TooClose {
+                id: 3,
+                other: 4,
+                required: 3,
+                actual: 1,
+            }]
+        );
+    }
+}
@@ -0,0 +1,95 @@
+use crate::{Item, MyError, Placement, Position};
+use std::collections::BTreeMap;
+
+// Named warehouses, each one a Placement with its own layout, strategy and filters
+#[derive(Debug, Default)]
+pub struct Registry {
+    warehouses: BTreeMap<String, Placement>,
+}
+
+impl Registry {
+    pub fn new() -> Registry {
+        Registry::default()
+    }
+
+    pub fn add(&mut self, name: &str, placement: Placement) -> Result<(), MyError> {
+        if self.warehouses.contains_key(name) {
+            return Err(MyError::DuplicateWarehouse(name.to_string()));
+        }
+        self.warehouses.insert(name.to_string(), placement);
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Result<&Placement, MyError> {
+        self.warehouses
+            .get(name)
+            .ok_or_else(|| MyError::UnknownWarehouse(name.to_string()))
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Result<&mut Placement, MyError> {
+        self.warehouses
+            .get_mut(name)
+            .ok_or_else(|| MyError::UnknownWarehouse(name.to_string()))
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.warehouses.keys().map(|name| name.as_str())
+    }
+
+    // (warehouse, item) for every warehouse that stores this id
+    pub fn find_by_id(&self, id: u32) -> Vec<(&str, &Item)> {
+        self.warehouses
+            .iter()
+            .filter_map(|(name, placement)| Some((name.as_str(), placement.storage.items.get(&id)?)))
+            .collect()
+    }
+
+    // every item with this name, in every warehouse
+    pub fn find_by_name(&self, item_name: &str) -> Vec<(&str, &Item)> {
+        let mut found: Vec<(&str, &Item)> = self
+            .warehouses
+            .iter()
+            .flat_map(|(name, placement)| {
+                placement
+                    .storage
+                    .items
+                    .values()
+                    .filter(|item| item.name == item_name)
+                    .map(move |item| (name.as_str(), item))
+            })
+            .collect();
+        found.sort_by_key(|(name, item)| (*name, item.id));
+        found
+    }
+
+    // Moves the whole item to another warehouse, where that warehouse's strategy and filters
+    // decide where it goes. Nothing changes if the destination refuses it.
+    pub fn transfer(&mut self, id: u32, from: &str, to: &str) -> Result<Vec<Position>, MyError> {
+        if from == to {
+            return Err(MyError::WrongOption(format!(
+                "cannot transfer from {} to itself",
+                from
+            )));
+        }
+
+        let source = self.get(from)?;
+        let item = source.storage.items.get(&id).ok_or(MyError::UnknownId(id))?.clone();
+        if source.available_quantity(id) < item.quantity {
+            return Err(MyError::ItemReserved(id));
+        }
+
+        let lot = source.catalog.lot_record(id);
+
+        let destination = self.get_mut(to)?;
+        destination.add_item(item)?;
+        // the lot keeps its sku, lot number and intake date in the new warehouse
+        if let Some((info, product)) = lot {
+            destination.catalog.adopt_lot(id, info, product);
+        }
+        let positions = destination.position_map.get(&id).cloned().unwrap_or_default();
+
+        // cannot fail anymore, the item is known to be in the source
+        self.get_mut(from)?.remove_item(id)?;
+        Ok(positions)
+    }
+}